@@ -0,0 +1,207 @@
+#![deny(unused_results)]
+
+use std::{fs, io::{self, Read, Write}, process::ExitCode};
+use bcbc::{byte_storage::{SliceInput, VecOutput}, Registry, Type, Value};
+
+const USAGE: &str = "\
+usage: bcbc <command> [options] [file]
+
+commands:
+    dump        pretty-print a binary value
+    encode      text, or JSON with --json, to binary
+    decode      binary to text, or JSON with --json
+    validate    decode and check against --schema, as --type if given
+    hex         hex dump of the input, with the structure annotated below each row
+    explain     annotate every byte of a binary value, up to the failure if malformed
+
+options:
+    --json             JSON instead of the text notation
+    --schema <file>    bcs schema, for validate and registry-guided JSON
+    --type <type>      type to check or to read JSON as, e.g. `list<struct#std:01:0002>`
+    -o <file>          output to the file instead of stdout
+
+the input is read from stdin if no file is given
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Dump,
+    Encode,
+    Decode,
+    Validate,
+    Hex,
+    Explain,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Args {
+    command: Option<Command>,
+    json: bool,
+    schema: Option<String>,
+    ty: Option<Type>,
+    input: Option<String>,
+    output: Option<String>,
+}
+
+type CliResult<T> = Result<T, String>;
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> CliResult<Args> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("missing value of {}", name));
+        match arg.as_str() {
+            "--json" => parsed.json = true,
+            "--schema" => parsed.schema = Some(value("--schema")?),
+            "--type" => parsed.ty = Some(value("--type")?.parse().map_err(|e| format!("invalid type: {}", e))?),
+            "-o" => parsed.output = Some(value("-o")?),
+            arg if arg.starts_with('-') && arg != "-" => return Err(format!("unknown option {}", arg)),
+            arg if parsed.command.is_none() => {
+                parsed.command = Some(match arg {
+                    "dump" => Command::Dump,
+                    "encode" => Command::Encode,
+                    "decode" => Command::Decode,
+                    "validate" => Command::Validate,
+                    "hex" => Command::Hex,
+                    "explain" => Command::Explain,
+                    _ => return Err(format!("unknown command {}", arg)),
+                });
+            }
+            arg if parsed.input.is_none() => parsed.input = Some(arg.to_owned()),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+    Ok(parsed)
+}
+
+fn load_schema(text: &str) -> CliResult<Registry> {
+    let schema = bcs_parser::parse(text).map_err(|e| format!("schema: {}", e))?;
+    let resolved = schema.resolve().map_err(|e| format!("schema: {}", e))?;
+    Ok(resolved.registry)
+}
+
+fn decode(buf: &[u8]) -> CliResult<Value<&[u8]>> {
+    Value::decode::<SliceInput>(buf).map_err(|e| format!("decode error: {}", e))
+}
+
+// rows of 16 bytes, each followed by the notes of `Value::explain` starting in it, indented by depth, and a failure at
+// the end of the input below the last row
+fn hex_dump(buf: &[u8]) -> String {
+    let notes = Value::explain::<SliceInput>(buf).notes;
+    let mut notes = notes.iter().peekable();
+    let mut out = String::new();
+    for (i, row) in buf.chunks(16).enumerate() {
+        out.push_str(&format!("{:08x} ", i * 16));
+        for j in 0..16 {
+            match row.get(j) {
+                Some(b) => out.push_str(&format!(" {:02x}", b)),
+                None => out.push_str("   "),
+            }
+        }
+        out.push_str("  |");
+        out.extend(row.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }));
+        out.push_str("|\n");
+        let end = if i * 16 + row.len() == buf.len() { usize::MAX } else { i * 16 + row.len() };
+        while let Some(note) = notes.next_if(|note| note.range.start < end) {
+            out.push_str(&format!("{:10}{:w$}{:04x}..{:04x} {}\n", "", "", note.range.start, note.range.end, note.kind, w = note.depth * 2));
+        }
+    }
+    // an empty input has no rows
+    for note in notes {
+        out.push_str(&format!("{:10}{:04x}..{:04x} {}\n", "", note.range.start, note.range.end, note.kind));
+    }
+    out
+}
+
+fn run(args: &Args, input: &[u8], reg: Option<&Registry>) -> CliResult<Vec<u8>> {
+    let text = || std::str::from_utf8(input).map_err(|e| format!("input is not UTF-8: {}", e));
+    let command = args.command.ok_or("missing command")?;
+    if args.ty.is_some() && !(command == Command::Validate || command == Command::Encode && args.json) {
+        return Err("--type is only used by validate and encode --json".to_owned());
+    }
+    Ok(match command {
+        Command::Dump => format!("{:#}\n", decode(input)?).into_bytes(),
+        Command::Encode => {
+            let val = if args.json {
+                let j: serde_json::Value = serde_json::from_str(text()?).map_err(|e| format!("invalid JSON: {}", e))?;
+                match (reg, &args.ty) {
+                    (Some(reg), Some(t)) => Value::from_json_with(&j, t, reg),
+                    (Some(_), None) => return Err("--schema needs --type to read JSON".to_owned()),
+                    (None, Some(t)) => Value::from_json_with(&j, t, &Registry::default()),
+                    (None, None) => Value::from_json(&j),
+                }.map_err(|e| format!("JSON error: {}", e))?
+            } else {
+                bcs_parser::parse_value(text()?).map_err(|e| e.to_string())?
+            };
+            val.encode::<VecOutput>().map_err(|e| format!("encode error: {}", e))?
+        }
+        Command::Decode => {
+            let val = decode(input)?;
+            if args.json {
+                let j = match reg {
+                    Some(reg) => val.to_json_with(reg),
+                    None => val.to_json(),
+                };
+                let mut out = serde_json::to_vec_pretty(&j).map_err(|e| e.to_string())?;
+                out.push(b'\n');
+                out
+            } else {
+                format!("{}\n", val).into_bytes()
+            }
+        }
+        Command::Validate => {
+            let (val, span) = Value::decode_spanned::<SliceInput>(input).map_err(|e| format!("decode error: {}", e))?;
+            let res = match (reg, &args.ty) {
+                (Some(reg), Some(t)) => reg.check(&val, t),
+                (Some(reg), None) => reg.typecheck(&val),
+                (None, Some(t)) => val.check(t),
+                (None, None) => Ok(()),
+            };
+            res.map_err(|e| match span.range_at(&e.path) {
+                Some(range) => format!("type error: {}, bytes {}..{}", e, range.start, range.end),
+                None => format!("type error: {}", e),
+            })?;
+            b"ok\n".to_vec()
+        }
+        Command::Hex => hex_dump(input).into_bytes(),
+        Command::Explain => Value::explain::<SliceInput>(input).to_string().into_bytes(),
+    })
+}
+
+fn main_inner() -> CliResult<()> {
+    let args = parse_args(std::env::args().skip(1))?;
+    if args.command.is_none() {
+        return Err(USAGE.to_owned());
+    }
+    let io_err = |name: &str, e: io::Error| format!("{}: {}", name, e);
+    let input = match args.input.as_deref() {
+        None | Some("-") => {
+            let mut buf = Vec::new();
+            let _ = io::stdin().read_to_end(&mut buf).map_err(|e| io_err("stdin", e))?;
+            buf
+        }
+        Some(path) => fs::read(path).map_err(|e| io_err(path, e))?,
+    };
+    let reg = match &args.schema {
+        Some(path) => Some(load_schema(&fs::read_to_string(path).map_err(|e| io_err(path, e))?)?),
+        None => None,
+    };
+    let out = run(&args, &input, reg.as_ref())?;
+    match &args.output {
+        Some(path) => fs::write(path, out).map_err(|e| io_err(path, e)),
+        None => io::stdout().write_all(&out).map_err(|e| io_err("stdout", e)),
+    }
+}
+
+fn main() -> ExitCode {
+    match main_inner() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e.trim_end());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,132 @@
+use crate::*;
+
+fn args(s: &str) -> Args {
+    parse_args(s.split_whitespace().map(str::to_owned)).unwrap()
+}
+
+fn run_bin(s: &str, input: &[u8], reg: Option<&Registry>) -> CliResult<Vec<u8>> {
+    run(&args(s), input, reg)
+}
+
+fn run_str(s: &str, input: &[u8], reg: Option<&Registry>) -> CliResult<String> {
+    run_bin(s, input, reg).map(|out| String::from_utf8(out).unwrap())
+}
+
+const SCHEMA: &str = "
+struct Point std(0x01, 0x0002) { x: i64, y: i64 }
+cenum Kind std(0x01, 0x0003) { A, B }
+";
+
+#[test]
+fn parse() {
+    assert_eq!(args("decode --json -o out.json in.bcbc"), Args {
+        command: Some(Command::Decode),
+        json: true,
+        schema: None,
+        ty: None,
+        input: Some("in.bcbc".to_owned()),
+        output: Some("out.json".to_owned()),
+    });
+    assert_eq!(args("validate --type list<u8> --schema a.bcs").ty, Some(Type::List(Box::new(Type::U8))));
+    let err = |s: &str| parse_args(s.split_whitespace().map(str::to_owned)).unwrap_err();
+    assert_eq!(err("frobnicate"), "unknown command frobnicate");
+    assert_eq!(err("dump --yaml"), "unknown option --yaml");
+    assert_eq!(err("dump a b"), "unexpected argument b");
+    assert_eq!(err("dump --schema"), "missing value of --schema");
+    assert_eq!(err("dump --type list<"), "invalid type: expected type at 5");
+}
+
+#[test]
+fn commands() {
+    let text = "struct#std:01:0002(1i64, -2i64)";
+    let bin = run_bin("encode", text.as_bytes(), None).unwrap();
+    assert_eq!(run_str("decode", &bin, None).unwrap(), format!("{}\n", text));
+    assert_eq!(run_str("dump", &bin, None).unwrap(), "struct#std:01:0002(\n    1i64,\n    -2i64,\n)\n");
+    assert_eq!(run_str("hex", &bin, None).unwrap(), format!("\
+00000000  f2 01 00 02 07 01 0a 02{}  |........|
+          0000..0001 H4::Struct len=2
+            0001..0004 TypeId std:01:0002
+            0004..0005 H4::N1 L4::P64
+              0005..0006 1i64
+            0006..0007 H4::N1 L4::N64
+              0007..0008 -2i64
+", " ".repeat(24)));
+    // notes below the row they start in, the failure below the last
+    let long = run_bin("encode", br#"tuple("abcdefghijklm", 1u8)"#, None).unwrap();
+    assert_eq!(run_str("hex", &long, None).unwrap(), format!("\
+00000000  c2 8c 0d 61 62 63 64 65 66 67 68 69 6a 6b 6c 6d  |...abcdefghijklm|
+          0000..0001 H4::Tuple len=2
+            0001..0002 H4::String len=ext8
+              0002..0003 extvar 13
+              0003..0010 \"abcdefghijklm\"
+00000010  00 01{}  |..|
+            0010..0011 H4::N1 L4::U8
+              0011..0012 1u8
+", " ".repeat(42)));
+    assert!(run_str("hex", &long[..16], None).unwrap().ends_with("\
+              0003..0010 \"abcdefghijklm\"
+            0010..0010 error: read error: unexpected end of input: 1 bytes needed, 0 left
+"));
+    assert_eq!(run_str("hex", b"", None).unwrap(), "          0000..0000 error: read error: unexpected end of input: 1 bytes needed, 0 left\n");
+    assert_eq!(run_str("explain", &bin, None).unwrap(), "\
+0000..0001  f2 → H4::Struct len=2
+0001..0004    01 00 02 → TypeId std:01:0002
+0004..0005    07 → H4::N1 L4::P64
+0005..0006      01 → 1i64
+0006..0007    0a → H4::N1 L4::N64
+0007..0008      02 → -2i64
+");
+    assert_eq!(run_str("explain", &[0x06], None).unwrap(), "\
+0000..0001  06 → H4::N1 L4::P32
+0001..0001  → error: read error: unexpected end of input: 1 bytes needed, 0 left
+");
+
+    // lossless JSON and back
+    let json = run_str("decode --json", &bin, None).unwrap();
+    assert_eq!(run_bin("encode --json", json.as_bytes(), None).unwrap(), bin);
+
+    // registry-guided JSON and back
+    let reg = load_schema(SCHEMA).unwrap();
+    let json = run_str("decode --json", &bin, Some(&reg)).unwrap();
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&json).unwrap(), serde_json::json!({ "x": 1, "y": -2 }));
+    assert_eq!(run_bin("encode --json --type struct#std:01:0002", json.as_bytes(), Some(&reg)).unwrap(), bin);
+    assert_eq!(run_bin("encode --json", json.as_bytes(), Some(&reg)).unwrap_err(), "--schema needs --type to read JSON");
+    assert_eq!(run_bin("encode --json --type list<u8>", b"[1, 2]", None).unwrap(), run_bin("encode", b"list<u8>[1u8, 2u8]", None).unwrap());
+    assert_eq!(run_bin("encode --type list<u8>", b"list<u8>[1u8]", None).unwrap_err(), "--type is only used by validate and encode --json");
+    assert_eq!(run_bin("decode --json --type list<u8>", &bin, None).unwrap_err(), "--type is only used by validate and encode --json");
+    assert_eq!(
+        run_bin("encode --json --type struct#std:01:0002", br#"{ "x": 1, "y": "a" }"#, Some(&reg)).unwrap_err(),
+        "JSON error: expected integer at struct(std:01:0002)[1]",
+    );
+}
+
+#[test]
+fn validate() {
+    let reg = load_schema(SCHEMA).unwrap();
+    let bin = |s: &str| run_bin("encode", s.as_bytes(), None).unwrap();
+    assert_eq!(run_str("validate", &bin("struct#std:01:0002(1i64, 2i64)"), Some(&reg)).unwrap(), "ok\n");
+    assert_eq!(run_str("validate --type list<u8>", &bin("list<u8>[1u8]"), None).unwrap(), "ok\n");
+    assert_eq!(
+        run_str("validate", &bin("struct#std:01:0002(1i64)"), Some(&reg)).unwrap_err(),
+        "type error: expected 2 fields of std:01:0002, found 1 at root, bytes 0..6",
+    );
+    assert_eq!(
+        run_str("validate", &bin("cenum#std:01:0003(5)"), Some(&reg)).unwrap_err(),
+        "type error: unknown variant 5 of std:01:0003 at root, bytes 0..4",
+    );
+    assert_eq!(
+        run_str("validate --type list<u16>", &bin("list<u8>[1u8]"), None).unwrap_err(),
+        "type error: expected list<u16>, found list<u8> at root, bytes 0..4",
+    );
+    assert_eq!(
+        run_str("validate", &bin("struct#std:01:0002(1i64, 2u8)"), Some(&reg)).unwrap_err(),
+        "type error: expected i64, found u8 at struct(std:01:0002)[1], bytes 6..8",
+    );
+    assert_eq!(run_str("validate", &[0x06], None).unwrap_err(), "decode error: read error: unexpected end of input: 1 bytes needed, 0 left at byte 1 in root, near 06 |");
+    assert_eq!(
+        run_str("validate", &[0xa1, 0x06, 0x06], None).unwrap_err(),
+        "decode error: read error: unexpected end of input: 1 bytes needed, 0 left at byte 3 in list[0], near a1 06 06 |",
+    );
+    assert_eq!(load_schema("struct A std(1, 1) { x: B }").unwrap_err(), "schema: undefined type B in A");
+    assert_eq!(load_schema("struct A").unwrap_err(), "schema: expected type id at 8");
+}
@@ -0,0 +1,351 @@
+#![deny(unused_results)]
+
+use proc_macro2::{Ident, Span, TokenStream, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    parse_macro_input, parse_quote, punctuated::Punctuated, Data, DataEnum, DeriveInput, Error, Expr, ExprLit, Fields,
+    GenericParam, Lit, LitInt, LitStr, Token, WherePredicate,
+};
+
+// must be the same as in bcbc::typeid
+const SCHEMA_ANONYMOUS: u8 = 0x00;
+const SCHEMA_HASH: u8 = 0xff;
+
+// Mapping:
+// - structs with named fields, tuple structs with zero or more than one field and unit structs => `Struct`
+// - tuple structs with exactly one field (newtypes) => `Alias`
+// - enums without any fields => `CEnum`
+// - other enums => `Enum`, with the payload being `Unit`, the single field, a `Tuple` of the fields,
+//   or an anonymous `Struct` of the named fields
+// Variant ids are the discriminants if written, counting up like in Rust otherwise.
+// Bounds are `T: Schema<__B>` for each type parameter `T`, and `F: Schema<__B>` for each field type `F` not mentioning
+// the type itself, as those of recursive types would overflow. Mutually recursive types need `#[bcbc(bound = "..")]`,
+// which replaces the inferred bounds.
+
+/// Variant ids must be distinct, including those counted up from a written one:
+///
+/// ```
+/// #[derive(bcbc_derive::Schema)]
+/// #[bcbc(anonymous)]
+/// enum Color {
+///     Red = 1,
+///     Green = 0,
+///     Blue = 2,
+/// }
+/// ```
+///
+/// ```compile_fail
+/// #[derive(bcbc_derive::Schema)]
+/// #[bcbc(anonymous)]
+/// enum Color {
+///     Red = 1,
+///     Green = 0,
+///     Blue,
+/// }
+/// ```
+#[proc_macro_derive(Schema, attributes(bcbc))]
+pub fn derive_schema(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let (id, bound) = attrs(&input)?;
+    let name = &input.ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let mut generics = input.generics.clone();
+    generics.params.push(parse_quote!(__B));
+    let field_tys: Vec<_> = match &input.data {
+        Data::Struct(data) => data.fields.iter().map(|f| &f.ty).collect(),
+        Data::Enum(data) => data.variants.iter().flat_map(|v| v.fields.iter().map(|f| &f.ty)).collect(),
+        Data::Union(_) => return Err(Error::new_spanned(name, "unions are not supported")),
+    };
+    let params: Vec<_> = input.generics.params.iter().filter_map(|p| match p {
+        GenericParam::Type(p) => Some(&p.ident),
+        _ => None,
+    }).collect();
+    let predicates = match bound {
+        Some(bound) => bound,
+        None => {
+            let mut predicates: Vec<WherePredicate> = params.iter().map(|p| parse_quote!(#p: ::bcbc::Schema<__B>)).collect();
+            let self_ = Ident::new("Self", Span::call_site());
+            let skip = [name, &self_];
+            for ty in field_tys {
+                if !mentions(ty.to_token_stream(), &skip) {
+                    predicates.push(parse_quote!(#ty: ::bcbc::Schema<__B>));
+                }
+            }
+            predicates
+        }
+    };
+    generics.make_where_clause().predicates.extend(predicates);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (ser, de) = match &input.data {
+        Data::Struct(data) => struct_impl(&data.fields),
+        Data::Enum(data) => enum_impl(data)?,
+        Data::Union(_) => unreachable!(),
+    };
+    let kind = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Unnamed(f) if f.unnamed.len() == 1 => quote!(Alias),
+            _ => quote!(Struct),
+        },
+        Data::Enum(data) if data.variants.iter().all(|v| matches!(v.fields, Fields::Unit)) => quote!(CEnum),
+        Data::Enum(_) => quote!(Enum),
+        Data::Union(_) => unreachable!(),
+    };
+    Ok(quote! {
+        impl #impl_generics ::bcbc::Schema<__B> for #name #ty_generics #where_clause {
+            const ID: ::bcbc::TypeId = #id;
+
+            fn ty() -> ::bcbc::Type {
+                ::bcbc::Type::#kind(#id)
+            }
+
+            fn serialize(self) -> ::bcbc::Value<__B> {
+                const ID: ::bcbc::TypeId = #id;
+                #ser
+            }
+
+            fn deserialize(val: ::bcbc::Value<__B>) -> ::bcbc::SchemaResult<Self> {
+                const ID: ::bcbc::TypeId = #id;
+                #de
+            }
+        }
+    })
+}
+
+fn mentions(tokens: TokenStream, idents: &[&Ident]) -> bool {
+    tokens.into_iter().any(|tt| match tt {
+        TokenTree::Ident(i) => idents.contains(&&i),
+        TokenTree::Group(g) => mentions(g.stream(), idents),
+        _ => false,
+    })
+}
+
+// the type id, `#[bcbc(std(schema = 0x01, id = 0x0001))]`, `#[bcbc(hash = "path")]` or `#[bcbc(anonymous)]`,
+// and the bounds of `#[bcbc(bound = "T: Schema<__B>")]` if any
+fn attrs(input: &DeriveInput) -> syn::Result<(TokenStream, Option<Vec<WherePredicate>>)> {
+    let mut id = None;
+    let mut bound = None;
+    for attr in &input.attrs {
+        if !attr.path().is_ident("bcbc") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            let new = if meta.path.is_ident("std") {
+                let (mut schema, mut sid) = (None, None);
+                meta.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("schema") {
+                        schema = Some(meta.value()?.parse::<LitInt>()?.base10_parse::<u8>()?);
+                    } else if meta.path.is_ident("id") {
+                        sid = Some(meta.value()?.parse::<LitInt>()?.base10_parse::<u16>()?);
+                    } else {
+                        return Err(meta.error("expected `schema` or `id`"));
+                    }
+                    Ok(())
+                })?;
+                let (Some(schema), Some(sid)) = (schema, sid) else {
+                    return Err(meta.error("expected both `schema` and `id`"));
+                };
+                if schema == SCHEMA_ANONYMOUS || schema == SCHEMA_HASH {
+                    return Err(meta.error("schema 0x00 and 0xff are reserved"));
+                }
+                quote! {
+                    ::bcbc::TypeId::Std(match ::bcbc::StdId::from_inner(#schema, #sid) {
+                        ::core::option::Option::Some(id) => id,
+                        ::core::option::Option::None => unreachable!(),
+                    })
+                }
+            } else if meta.path.is_ident("hash") {
+                let path = meta.value()?.parse::<LitStr>()?;
+                let hash = hash_path(&path.value());
+                quote!(::bcbc::TypeId::Hash(::bcbc::HashId::from_hash([#(#hash),*])))
+            } else if meta.path.is_ident("anonymous") {
+                quote!(::bcbc::TypeId::Anonymous)
+            } else if meta.path.is_ident("bound") {
+                let lit = meta.value()?.parse::<LitStr>()?;
+                let predicates = lit.parse_with(Punctuated::<WherePredicate, Token![,]>::parse_terminated)?;
+                if bound.replace(predicates.into_iter().collect()).is_some() {
+                    return Err(meta.error("duplicate bound"));
+                }
+                return Ok(());
+            } else {
+                return Err(meta.error("expected `std`, `hash`, `anonymous` or `bound`"));
+            };
+            if id.replace(new).is_some() {
+                return Err(meta.error("duplicate type id"));
+            }
+            Ok(())
+        })?;
+    }
+    let id = id.ok_or_else(|| Error::new(
+        Span::call_site(),
+        "expected `#[bcbc(std(schema = .., id = ..))]`, `#[bcbc(hash = \"..\")]` or `#[bcbc(anonymous)]`",
+    ))?;
+    Ok((id, bound))
+}
+
+// must be the same as `bcbc::HashId::from_path`, which is not const, checked by the `derive` test
+#[allow(deprecated)]
+fn hash_path(path: &str) -> [u8; 7] {
+    use std::hash::{Hash, Hasher, SipHasher};
+    let mut hasher = SipHasher::new();
+    path.hash(&mut hasher);
+    hasher.finish().to_be_bytes()[1..].try_into().unwrap()
+}
+
+// prepends segments to the path of errors from inner values
+fn at<'a>(segs: impl IntoIterator<Item = &'a TokenStream>) -> TokenStream {
+    let segs: Vec<_> = segs.into_iter().collect();
+    if segs.is_empty() {
+        return quote!();
+    }
+    quote!(.map_err(|e| e #(.at(#segs))*))
+}
+
+// returns the pattern binding the fields, the boxed slice of serialized fields,
+// and the block constructing `ctor` from `source` returning an array of values
+fn seq(
+    fields: &Fields,
+    kind: TokenStream,
+    ctor: TokenStream,
+    source: TokenStream,
+    outer: Option<TokenStream>,
+) -> (TokenStream, TokenStream, TokenStream) {
+    let binds: Vec<_> = (0..fields.len()).map(|i| format_ident!("__f{}", i)).collect();
+    let outer_at = at(&outer);
+    let inners = binds.iter().enumerate().map(|(i, bind)| {
+        let inner_at = at([&quote!(::bcbc::PathSeg::Index(#kind, #i))].into_iter().chain(&outer));
+        quote!(::bcbc::Schema::<__B>::deserialize(#bind) #inner_at ?)
+    });
+    let (pat, construct) = match fields {
+        Fields::Named(f) => {
+            let names: Vec<_> = f.named.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+            (quote!({ #(#names: #binds),* }), quote!(#ctor { #(#names: #inners),* }))
+        }
+        Fields::Unnamed(_) => (quote!(( #(#binds),* )), quote!(#ctor( #(#inners),* ))),
+        Fields::Unit => (quote!(), quote!(#ctor)),
+    };
+    let ser = quote!(::bcbc::__private::vec![ #(::bcbc::Schema::<__B>::serialize(#binds)),* ].into_boxed_slice());
+    let de = quote!({
+        let [#(#binds),*] = #source #outer_at ?;
+        ::core::result::Result::Ok(#construct)
+    });
+    (pat, ser, de)
+}
+
+fn struct_impl(fields: &Fields) -> (TokenStream, TokenStream) {
+    match fields {
+        Fields::Unnamed(f) if f.unnamed.len() == 1 => {
+            let alias_at = at([&quote!(::bcbc::PathSeg::Alias(ID))]);
+            (
+                quote!(::bcbc::Value::Alias(ID, ::bcbc::__private::Box::new(::bcbc::Schema::<__B>::serialize(self.0)))),
+                quote!(::core::result::Result::Ok(Self(::bcbc::Schema::<__B>::deserialize(val.into_alias_of(ID)?) #alias_at ?))),
+            )
+        }
+        fields => {
+            let n = fields.len();
+            let (pat, ser, de) = seq(fields, quote!(::bcbc::SeqKind::Struct(ID)), quote!(Self), quote!(val.into_struct_of::<#n>(ID)), None);
+            (
+                quote! {
+                    let Self #pat = self;
+                    ::bcbc::Value::Struct(ID, #ser)
+                },
+                de,
+            )
+        }
+    }
+}
+
+fn enum_impl(data: &DataEnum) -> syn::Result<(TokenStream, TokenStream)> {
+    let mut evs = Vec::new();
+    let mut ev = 0u64;
+    for variant in &data.variants {
+        if let Some((_, expr)) = &variant.discriminant {
+            ev = match expr {
+                Expr::Lit(ExprLit { lit: Lit::Int(i), .. }) => i.base10_parse()?,
+                expr => return Err(Error::new_spanned(expr, "expected an integer literal")),
+            };
+        }
+        // also catches ids counted up (and wrapped around) into a written one
+        if evs.contains(&ev) {
+            return Err(Error::new_spanned(&variant.ident, format!("duplicate variant id {}", ev)));
+        }
+        evs.push(ev);
+        ev = ev.wrapping_add(1);
+    }
+    let idents: Vec<_> = data.variants.iter().map(|v| &v.ident).collect();
+    let unknown = quote! {
+        ev => ::core::result::Result::Err(::bcbc::SchemaError::new(::bcbc::SchemaErrorKind::Variant { id: ID, ev })),
+    };
+
+    if data.variants.iter().all(|v| matches!(v.fields, Fields::Unit)) {
+        return Ok((
+            quote! {
+                match self {
+                    #(Self::#idents => ::bcbc::Value::CEnum(ID, #evs),)*
+                }
+            },
+            quote! {
+                match val.into_c_enum_of(ID)? {
+                    #(#evs => ::core::result::Result::Ok(Self::#idents),)*
+                    #unknown
+                }
+            },
+        ));
+    }
+
+    let mut ser_arms = Vec::new();
+    let mut de_arms = Vec::new();
+    for ((variant, ident), ev) in data.variants.iter().zip(&idents).zip(&evs) {
+        let ctor = quote!(Self::#ident);
+        let variant_seg = quote!(::bcbc::PathSeg::Variant(ID, #ev));
+        let variant_at = at([&variant_seg]);
+        let n = variant.fields.len();
+        let (pat, payload, de) = match &variant.fields {
+            Fields::Unit => (
+                quote!(),
+                quote!(::bcbc::Value::Unit),
+                quote!({
+                    v.into_unit() #variant_at ?;
+                    ::core::result::Result::Ok(#ctor)
+                }),
+            ),
+            Fields::Unnamed(_) if n == 1 => (
+                quote!((__f0)),
+                quote!(::bcbc::Schema::<__B>::serialize(__f0)),
+                quote!(::core::result::Result::Ok(#ctor(::bcbc::Schema::<__B>::deserialize(v) #variant_at ?))),
+            ),
+            fields @ Fields::Unnamed(_) => {
+                let (pat, ser, de) = seq(fields, quote!(::bcbc::SeqKind::Tuple), ctor, quote!(v.into_tuple_of::<#n>()), Some(variant_seg));
+                (pat, quote!(::bcbc::Value::Tuple(#ser)), de)
+            }
+            fields @ Fields::Named(_) => {
+                let source = quote!(v.into_struct_of::<#n>(::bcbc::TypeId::Anonymous));
+                let (pat, ser, de) = seq(fields, quote!(::bcbc::SeqKind::Struct(::bcbc::TypeId::Anonymous)), ctor, source, Some(variant_seg));
+                (pat, quote!(::bcbc::Value::Struct(::bcbc::TypeId::Anonymous, #ser)), de)
+            }
+        };
+        ser_arms.push(quote! {
+            Self::#ident #pat => ::bcbc::Value::Enum(ID, #ev, ::bcbc::__private::Box::new(#payload)),
+        });
+        de_arms.push(quote! {
+            #ev => #de,
+        });
+    }
+    Ok((
+        quote! {
+            match self {
+                #(#ser_arms)*
+            }
+        },
+        quote! {
+            let (ev, v) = val.into_enum_of(ID)?;
+            match ev {
+                #(#de_arms)*
+                #unknown
+            }
+        },
+    ))
+}
@@ -28,6 +28,61 @@ pub fn bytevar_flen(buf: &[u8; 8]) -> usize {
     1
 }
 
+// `F16`s are only carried as bits
+pub fn f16_to_f32(bits: u16) -> f32 {
+    let sign = u32::from(bits >> 15) << 31;
+    let exp = u32::from((bits >> 10) & 0x1f);
+    let frac = u32::from(bits & 0x3ff);
+    let abs = match (exp, frac) {
+        (0, 0) => 0,
+        (0, frac) => {
+            // subnormal, frac * 2^-24
+            let v = frac as f32 * f32::from_bits(0x3380_0000);
+            return if sign == 0 { v } else { -v };
+        }
+        (0x1f, frac) => 0x7f80_0000 | (frac << 13),
+        (exp, frac) => ((exp + 127 - 15) << 23) | (frac << 13),
+    };
+    f32::from_bits(sign | abs)
+}
+
+// rounds to nearest, ties to even
+pub fn f32_to_f16(f: f32) -> u16 {
+    // exact, so rounded only once
+    f64_to_f16(f64::from(f))
+}
+
+// rounds to nearest, ties to even, so that parsing a decimal through `f64` rounds (almost always) once
+pub fn f64_to_f16(f: f64) -> u16 {
+    let bits = f.to_bits();
+    let sign = ((bits >> 48) & 0x8000) as u16;
+    let exp = ((bits >> 52) & 0x7ff) as i32;
+    let frac = bits & 0xf_ffff_ffff_ffff;
+    if exp == 0x7ff {
+        // keeps NaNs NaN
+        return sign | 0x7c00 | if frac != 0 { 0x200 | (frac >> 42) as u16 } else { 0 };
+    }
+    let exp = exp - 1023 + 15;
+    if exp >= 0x1f {
+        return sign | 0x7c00;
+    }
+    let (frac, shift) = match exp {
+        // too small even for subnormals
+        ..=-11 => return sign,
+        // subnormal, with the implicit bit
+        -10..=0 => (frac | 0x10_0000_0000_0000, (43 - exp) as u32),
+        _ => (((exp as u64) << 52) | frac, 42),
+    };
+    let mut h = (frac >> shift) as u16;
+    let rest = frac & ((1 << shift) - 1);
+    let half = 1 << (shift - 1);
+    // may carry into the exponent, up to infinity
+    if rest > half || (rest == half && h & 1 == 1) {
+        h += 1;
+    }
+    sign | h
+}
+
 #[inline]
 pub const fn from_h4l4(h4: H4, l4: L4) -> u8 {
     (h4 as u8) << 4 | (l4 as u8)
@@ -71,6 +126,14 @@ impl H4 {
         })
     }
 
+    // only the first codes of EXT2 are assigned, so this can fail on input
+    pub fn to_ext2(self) -> Result<Ext2> {
+        if !self.is_num() {
+            return Err(Fatal::H4ToExt2(self).into());
+        }
+        (self as u8).try_into()
+    }
+
     pub const fn from_bytevar_len(pos: usize) -> FatalResult<H4> {
         Ok(match pos {
             1 => H4::N1,
@@ -97,6 +160,14 @@ impl H4 {
             Ext1::TypeId => H4::N8,
         }
     }
+
+    pub const fn from_ext2(ext2: Ext2) -> H4 {
+        match ext2 {
+            Ext2::Char => H4::N1,
+            Ext2::U128 => H4::N2,
+            Ext2::I128 => H4::N3,
+        }
+    }
 }
 
 impl Type {
@@ -129,6 +200,60 @@ impl Type {
                 F16
                 F32
                 F64
+                Char
+                U128
+                I128
+                String
+                Bytes
+                Type
+                TypeId
+            }
+            direct {
+                Option
+                List
+                Map
+                Tuple
+                Alias
+                Enum
+                CEnum
+                Struct
+            }
+        }
+    }
+
+    // the type of the tag if it has no inner types or type id, the inverse of `as_tag` for those
+    pub const fn from_scalar_tag(tag: Tag) -> Option<Type> {
+        macro_rules! from_scalar_tag_impl {
+            (
+                direct_empty {$($direct_empty_name:ident)*}
+                direct {$($direct_name:ident)*}
+            ) => {
+                match tag {
+                    $(Tag::$direct_empty_name => Some(Type::$direct_empty_name),)*
+                    $(Tag::$direct_name => None,)*
+                }
+            };
+        }
+
+        from_scalar_tag_impl! {
+            direct_empty {
+                Unknown
+                Unit
+                Bool
+                U8
+                U16
+                U32
+                U64
+                I8
+                I16
+                I32
+                I64
+                F16
+                F32
+                F64
+                Char
+                U128
+                I128
                 String
                 Bytes
                 Type
@@ -179,6 +304,9 @@ impl<B> Value<B> {
                 F16
                 F32
                 F64
+                Char
+                U128
+                I128
                 String
                 Bytes
                 Option
@@ -229,6 +357,9 @@ impl<B> Value<B> {
                 F16
                 F32
                 F64
+                Char
+                U128
+                I128
                 String
                 Bytes
                 Type
@@ -249,11 +380,11 @@ impl<B> Value<B> {
 }
 
 impl<B> Value<B> {
-    pub fn serialize_from<T: Schema>(val: T) -> Value<B> {
+    pub fn serialize_from<T: Schema<B>>(val: T) -> Value<B> {
         val.serialize()
     }
 
-    pub fn deserialize_into<T: Schema>(self) -> T {
+    pub fn deserialize_into<T: Schema<B>>(self) -> SchemaResult<T> {
         T::deserialize(self)
     }
 }
@@ -264,30 +395,45 @@ impl<B> Value<B> {
 //     }
 // }
 
+impl SchemaError {
+    pub const fn new(kind: SchemaErrorKind) -> SchemaError {
+        SchemaError { path: Path::new(), kind }
+    }
+
+    pub const fn tag(expected: Tag, found: Tag) -> SchemaError {
+        SchemaError::new(SchemaErrorKind::Tag { expected, found })
+    }
+
+    // errors are created at the inner value, so every container prepends its own segment
+    pub fn at(mut self, seg: PathSeg) -> SchemaError {
+        self.path.0.insert(0, seg);
+        self
+    }
+}
+
 macro_rules! into_impl {
     // TODO auto make fn name with concat_ident! and const case convert
     ($($fn_name:ident | $variant:ident)*) => {$(
-        pub fn $fn_name(self) {
-            if !matches!(self, Value::$variant) {
-                unreachable!()
+        pub fn $fn_name(self) -> SchemaResult<()> {
+            match self {
+                Value::$variant => Ok(()),
+                val => Err(SchemaError::tag(Tag::$variant, val.as_tag())),
             }
         }
     )*};
     ($($fn_name:ident -> $ty:ty | $variant:ident)*) => {$(
-        pub fn $fn_name(self) -> $ty {
-            if let Value::$variant(v) = self {
-                v
-            } else {
-                unreachable!()
+        pub fn $fn_name(self) -> SchemaResult<$ty> {
+            match self {
+                Value::$variant(v) => Ok(v),
+                val => Err(SchemaError::tag(Tag::$variant, val.as_tag())),
             }
         }
     )*};
     ($($fn_name:ident -> $ty:ty | $variant:ident($($val_name:ident$(,)*)*) -> $val_fn:block)*) => {$(
-        pub fn $fn_name(self) -> $ty {
-            if let Value::$variant($($val_name,)*) = self {
-                $val_fn
-            } else {
-                unreachable!()
+        pub fn $fn_name(self) -> SchemaResult<$ty> {
+            match self {
+                Value::$variant($($val_name,)*) => Ok($val_fn),
+                val => Err(SchemaError::tag(Tag::$variant, val.as_tag())),
             }
         }
     )*};
@@ -312,6 +458,9 @@ impl<B> Value<B> {
         into_f16 -> u16        | F16
         into_f32 -> u32        | F32
         into_f64 -> u64        | F64
+        into_char -> char      | Char
+        into_u128 -> u128      | U128
+        into_i128 -> i128      | I128
         into_string -> ByteStr<B> | String
         into_bytes -> B        | Bytes
         into_type -> Type      | Type
@@ -330,6 +479,50 @@ impl<B> Value<B> {
     }
 }
 
+// checked versions for implementing `Schema`
+impl<B> Value<B> {
+    pub(crate) fn expect_id(&self, expected: TypeId) -> SchemaResult<()> {
+        match self {
+            Value::Alias(found, ..)
+            | Value::CEnum(found, ..)
+            | Value::Enum(found, ..)
+            | Value::Struct(found, ..) if *found != expected => {
+                Err(SchemaError::new(SchemaErrorKind::TypeId { expected, found: *found }))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn into_alias_of(self, id: TypeId) -> SchemaResult<Value<B>> {
+        self.expect_id(id)?;
+        self.into_alias()
+    }
+
+    pub fn into_c_enum_of(self, id: TypeId) -> SchemaResult<EnumVariantId> {
+        self.expect_id(id)?;
+        self.into_c_enum()
+    }
+
+    pub fn into_enum_of(self, id: TypeId) -> SchemaResult<(EnumVariantId, Value<B>)> {
+        self.expect_id(id)?;
+        self.into_enum()
+    }
+
+    pub fn into_struct_of<const N: usize>(self, id: TypeId) -> SchemaResult<[Value<B>; N]> {
+        self.expect_id(id)?;
+        into_array(self.into_struct()?)
+    }
+
+    pub fn into_tuple_of<const N: usize>(self) -> SchemaResult<[Value<B>; N]> {
+        into_array(self.into_tuple()?)
+    }
+}
+
+fn into_array<B, const N: usize>(s: Box<[Value<B>]>) -> SchemaResult<[Value<B>; N]> {
+    let found = s.len();
+    s.into_vec().try_into().map_err(|_| SchemaError::new(SchemaErrorKind::Len { expected: N, found }))
+}
+
 #[allow(clippy::just_underscores_and_digits)]
 impl<B> Value<B> {
     // can only be function pointers
@@ -348,6 +541,9 @@ impl<B> Value<B> {
             Value::F16(_0) => Value::F16(_0),
             Value::F32(_0) => Value::F32(_0),
             Value::F64(_0) => Value::F64(_0),
+            Value::Char(_0) => Value::Char(_0),
+            Value::U128(_0) => Value::U128(_0),
+            Value::I128(_0) => Value::I128(_0),
             Value::String(_0) => Value::String(_0.map_bytes(f)),
             Value::Bytes(_0) => Value::Bytes(f(_0)),
             Value::Option(_0, _1) => Value::Option(_0, Box::new(_1.map(|v| v.map_bytes(f)))),
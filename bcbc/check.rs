@@ -0,0 +1,188 @@
+use super::*;
+
+impl Type {
+    // the most specific type matching both, with `Unknown` matching anything
+    pub fn meet(&self, other: &Type) -> Option<Type> {
+        Some(match (self, other) {
+            (Type::Unknown, t) | (t, Type::Unknown) => t.clone(),
+            (Type::Option(a), Type::Option(b)) => Type::Option(Box::new(a.meet(b)?)),
+            (Type::List(a), Type::List(b)) => Type::List(Box::new(a.meet(b)?)),
+            (Type::Map(ak, av), Type::Map(bk, bv)) => Type::Map(Box::new(ak.meet(bk)?), Box::new(av.meet(bv)?)),
+            (Type::Tuple(a), Type::Tuple(b)) if a.len() == b.len() => {
+                Type::Tuple(a.iter().zip(b.iter()).map(|(a, b)| a.meet(b)).collect::<Option<_>>()?)
+            }
+            (a, b) if a == b => a.clone(),
+            _ => return None,
+        })
+    }
+
+    pub fn matches(&self, other: &Type) -> bool {
+        self.meet(other).is_some()
+    }
+}
+
+// for streams, which check inner values as they come
+impl Type {
+    // of the `i`th inner value of a container of this type, keys and values both count for a map
+    pub(crate) fn inner(&self, i: usize) -> Type {
+        match self {
+            Type::Option(t) | Type::List(t) => (**t).clone(),
+            Type::Map(tk, tv) => match i % 2 {
+                0 => (**tk).clone(),
+                _ => (**tv).clone(),
+            },
+            Type::Tuple(ts) => ts.get(i).cloned().unwrap_or(Type::Unknown),
+            _ => Type::Unknown,
+        }
+    }
+}
+
+impl TypeError {
+    // prefixed with the path to the checked value
+    pub(crate) fn within(self, segs: impl IntoIterator<Item = PathSeg>) -> TypeError {
+        let mut path = segs.into_iter().collect::<Vec<_>>();
+        path.extend(self.path.0);
+        TypeError { path: Path(path), kind: self.kind }
+    }
+}
+
+impl<B> Event<B> {
+    // Checks the value this is, or the container this starts, against `expected`. Returns what the inner values of the
+    // container are checked against, from the types declared by both.
+    pub(crate) fn check(&self, expected: &Type) -> CheckResult<Type> {
+        let found = match self {
+            Event::Scalar(v) => {
+                v.check(expected)?;
+                return Ok(Type::Unknown);
+            }
+            Event::SomeStart { ty } => Type::Option(Box::new(ty.clone())),
+            Event::ListStart { ty, .. } => Type::List(Box::new(ty.clone())),
+            Event::MapStart { tk, tv, .. } => Type::Map(Box::new(tk.clone()), Box::new(tv.clone())),
+            // not built from the declared length, which is not checked against any limit yet
+            Event::TupleStart { len } => return match expected {
+                Type::Unknown => Ok(Type::Unknown),
+                Type::Tuple(ts) if ts.len() == *len => Ok(expected.clone()),
+                _ => {
+                    let kind = TypeErrorKind::TupleMismatch { expected: expected.clone(), len: *len };
+                    Err(TypeError { path: Path::new(), kind })
+                }
+            },
+            Event::AliasStart { id } => Type::Alias(*id),
+            Event::EnumStart { id, .. } => Type::Enum(*id),
+            Event::StructStart { id, .. } => Type::Struct(*id),
+            Event::End => return Ok(Type::Unknown),
+        };
+        expected.meet(&found).ok_or_else(|| {
+            let kind = TypeErrorKind::Mismatch { expected: expected.clone(), found };
+            TypeError { path: Path::new(), kind }
+        })
+    }
+}
+
+impl<B> Value<B> {
+    // Checks the value against `t` and every type declared inside the value against its inner values.
+    // Inner values of aliases, enums and structs are only checked against their own declared types,
+    // see `Registry::check` for checking them against definitions.
+    pub fn check(&self, t: &Type) -> CheckResult<()> {
+        self.check_impl(t, None)
+    }
+
+    // only checks the types declared inside the value
+    pub fn typecheck(&self) -> CheckResult<()> {
+        self.check_impl(&Type::Unknown, None)
+    }
+
+    // iterative like the reader, and reports the first mismatch in encoding order
+    pub(crate) fn check_impl(&self, t: &Type, reg: Option<&Registry>) -> CheckResult<()> {
+        let mut path = Vec::new();
+        let mut stack = vec![(0, None, self, t.clone())];
+        while let Some((depth, seg, val, expected)) = stack.pop() {
+            path.truncate(depth);
+            path.extend(seg);
+            let found = val.as_type();
+            let Some(t) = expected.meet(&found) else {
+                return Err(TypeError { path: Path(path), kind: TypeErrorKind::Mismatch { expected, found } });
+            };
+            let def = match &t {
+                Type::Alias(r)
+                | Type::CEnum(r)
+                | Type::Enum(r)
+                | Type::Struct(r) => reg.and_then(|reg| reg.get(r)).map(|def| (*r, def)),
+                _ => None,
+            };
+            if let Some((r, def)) = def {
+                if def.as_type(r) != t {
+                    let kind = TypeErrorKind::Mismatch { expected: def.as_type(r), found };
+                    return Err(TypeError { path: Path(path), kind });
+                }
+            }
+            let depth = path.len();
+            match (val, t, def.map(|(_, def)| def)) {
+                (Value::Option(_, v), Type::Option(t), _) => {
+                    if let Some(v) = v.as_ref() {
+                        stack.push((depth, Some(PathSeg::Some), v, *t));
+                    }
+                }
+                (Value::List(_, s), Type::List(t), _) => {
+                    for (i, v) in s.iter().enumerate().rev() {
+                        stack.push((depth, Some(PathSeg::Index(SeqKind::List, i)), v, (*t).clone()));
+                    }
+                }
+                (Value::Map(_, s), Type::Map(tk, tv), _) => {
+                    for (i, (k, v)) in s.iter().enumerate().rev() {
+                        stack.push((depth, Some(PathSeg::Value(i)), v, (*tv).clone()));
+                        stack.push((depth, Some(PathSeg::Key(i)), k, (*tk).clone()));
+                    }
+                }
+                (Value::Tuple(s), Type::Tuple(ts), _) => {
+                    for (i, (v, t)) in s.iter().zip(ts.into_vec()).enumerate().rev() {
+                        stack.push((depth, Some(PathSeg::Index(SeqKind::Tuple, i)), v, t));
+                    }
+                }
+                (Value::Alias(r, v), _, def) => {
+                    let t = match def {
+                        Some(Def::Alias { ty, .. }) => ty.clone(),
+                        _ => Type::Unknown,
+                    };
+                    stack.push((depth, Some(PathSeg::Alias(*r)), v, t));
+                }
+                (Value::CEnum(r, ev), _, Some(Def::CEnum { variants, .. }))
+                if !variants.iter().any(|v| v.id == *ev) => {
+                    let kind = TypeErrorKind::UnknownVariant { id: *r, ev: *ev };
+                    return Err(TypeError { path: Path(path), kind });
+                }
+                (Value::Enum(r, ev, v), _, def) => {
+                    let t = match def {
+                        Some(Def::Enum { variants, .. }) => match variants.iter().find(|v| v.id == *ev) {
+                            Some(variant) => variant.ty.clone(),
+                            None => {
+                                let kind = TypeErrorKind::UnknownVariant { id: *r, ev: *ev };
+                                return Err(TypeError { path: Path(path), kind });
+                            }
+                        },
+                        _ => Type::Unknown,
+                    };
+                    stack.push((depth, Some(PathSeg::Variant(*r, *ev)), v, t));
+                }
+                (Value::Struct(r, s), _, def) => {
+                    let fields = match def {
+                        Some(Def::Struct { fields, .. }) => {
+                            if fields.len() != s.len() {
+                                let kind = TypeErrorKind::FieldCount { id: *r, expected: fields.len(), found: s.len() };
+                                return Err(TypeError { path: Path(path), kind });
+                            }
+                            fields.as_slice()
+                        }
+                        _ => &[],
+                    };
+                    for (i, v) in s.iter().enumerate().rev() {
+                        let t = fields.get(i).map_or(Type::Unknown, |f| f.ty.clone());
+                        stack.push((depth, Some(PathSeg::Index(SeqKind::Struct(*r), i)), v, t));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,363 @@
+use alloc::{string::ToString, vec};
+use serde::de::{self, Deserialize, DeserializeSeed, IntoDeserializer, Visitor};
+use super::*;
+
+// Inverse of the mapping in `ser`. Enums also accept `Enum`s with `Unit` payloads for unit variants and
+// `CEnum`s for unit variants of any kind, so that values produced by `Schema` impls can be read.
+// Strings and bytes are borrowed from the input.
+
+impl de::Error for SerdeError {
+    fn custom<T: core::fmt::Display>(msg: T) -> SerdeError {
+        SerdeError::new(SerdeErrorKind::Custom(msg.to_string()))
+    }
+}
+
+pub fn from_value<'de, T: Deserialize<'de>>(val: Value<&'de [u8]>) -> SerdeResult<T> {
+    from_value_with(val, SerdeOptions::DEFAULT)
+}
+
+pub fn from_value_with<'de, T: Deserialize<'de>>(val: Value<&'de [u8]>, opts: SerdeOptions) -> SerdeResult<T> {
+    T::deserialize(Deserializer::new(val, opts))
+}
+
+pub fn from_slice<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> SerdeResult<T> {
+    from_slice_with(bytes, SerdeOptions::DEFAULT)
+}
+
+pub fn from_slice_with<'de, T: Deserialize<'de>>(bytes: &'de [u8], opts: SerdeOptions) -> SerdeResult<T> {
+    let val = Value::decode_with::<SliceInput>(bytes, opts.opts)
+        .map_err(|FullError { err, pos, .. }| SerdeError::new(SerdeErrorKind::Decode { err, pos }))?;
+    from_value_with(val, opts)
+}
+
+pub struct Deserializer<'de> {
+    val: Value<&'de [u8]>,
+    ids: fn(&'static str) -> TypeId,
+}
+
+impl<'de> Deserializer<'de> {
+    pub const fn new(val: Value<&'de [u8]>, opts: SerdeOptions) -> Deserializer<'de> {
+        Deserializer { val, ids: opts.ids }
+    }
+
+    fn fields(self, name: &'static str, len: usize) -> SerdeResult<Seq<'de>> {
+        let id = (self.ids)(name);
+        self.val.expect_id(id)?;
+        let s = self.val.into_struct()?;
+        expect_len(len, s.len())?;
+        Ok(Seq::new(self.ids, SeqKind::Struct(id), s))
+    }
+}
+
+fn expect_len(expected: usize, found: usize) -> SerdeResult<()> {
+    if expected != found {
+        return Err(SchemaError::new(SchemaErrorKind::Len { expected, found }).into());
+    }
+    Ok(())
+}
+
+fn borrow_str(s: ByteStr<&[u8]>) -> SerdeResult<&str> {
+    core::str::from_utf8(s.leak_bytes()).map_err(|e| SerdeError::new(SerdeErrorKind::Utf8(e)))
+}
+
+macro_rules! scalar_impl {
+    ($($fn_name:ident => $into:ident $visit:ident)*) => {$(
+        fn $fn_name<V: Visitor<'de>>(self, visitor: V) -> SerdeResult<V::Value> {
+            visitor.$visit(self.val.$into()?)
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> SerdeResult<V::Value> {
+        let ids = self.ids;
+        match self.val {
+            Value::Unit => visitor.visit_unit(),
+            Value::Bool(v) => visitor.visit_bool(v),
+            Value::U8(v) => visitor.visit_u8(v),
+            Value::U16(v) => visitor.visit_u16(v),
+            Value::U32(v) => visitor.visit_u32(v),
+            Value::U64(v) => visitor.visit_u64(v),
+            Value::I8(v) => visitor.visit_i8(v),
+            Value::I16(v) => visitor.visit_i16(v),
+            Value::I32(v) => visitor.visit_i32(v),
+            Value::I64(v) => visitor.visit_i64(v),
+            Value::F16(v) => visitor.visit_f32(casting::f16_to_f32(v)),
+            Value::F32(v) => visitor.visit_f32(f32::from_bits(v)),
+            Value::F64(v) => visitor.visit_f64(f64::from_bits(v)),
+            Value::Char(v) => visitor.visit_char(v),
+            Value::U128(v) => visitor.visit_u128(v),
+            Value::I128(v) => visitor.visit_i128(v),
+            Value::String(s) => visitor.visit_borrowed_str(borrow_str(s)?),
+            Value::Bytes(b) => visitor.visit_borrowed_bytes(b),
+            Value::Option(..) => self.deserialize_option(visitor),
+            Value::List(_, s) => visitor.visit_seq(Seq::new(ids, SeqKind::List, s)),
+            Value::Tuple(s) => visitor.visit_seq(Seq::new(ids, SeqKind::Tuple, s)),
+            Value::Struct(id, s) => visitor.visit_seq(Seq::new(ids, SeqKind::Struct(id), s)),
+            Value::Map(_, s) => visitor.visit_map(Map::new(ids, s)),
+            Value::Alias(id, v) => visitor.visit_newtype_struct(Deserializer { val: *v, ids }).map_err(|e| e.at(PathSeg::Alias(id))),
+            Value::CEnum(id, ev) => visitor.visit_enum(Enum { de: Deserializer { val: Value::Unit, ids }, id, ev, unit: true }),
+            Value::Enum(id, ev, v) => visitor.visit_enum(Enum { de: Deserializer { val: *v, ids }, id, ev, unit: false }),
+            Value::Type(_) => Err(SerdeError::new(SerdeErrorKind::Unsupported(Tag::Type))),
+            Value::TypeId(_) => Err(SerdeError::new(SerdeErrorKind::Unsupported(Tag::TypeId))),
+        }
+    }
+
+    scalar_impl! {
+        deserialize_bool => into_bool visit_bool
+        deserialize_u8 => into_u8 visit_u8
+        deserialize_u16 => into_u16 visit_u16
+        deserialize_u32 => into_u32 visit_u32
+        deserialize_u64 => into_u64 visit_u64
+        deserialize_u128 => into_u128 visit_u128
+        deserialize_i8 => into_i8 visit_i8
+        deserialize_i16 => into_i16 visit_i16
+        deserialize_i32 => into_i32 visit_i32
+        deserialize_i64 => into_i64 visit_i64
+        deserialize_i128 => into_i128 visit_i128
+        deserialize_char => into_char visit_char
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> SerdeResult<V::Value> {
+        match self.val {
+            Value::F16(v) => visitor.visit_f32(casting::f16_to_f32(v)),
+            val => visitor.visit_f32(f32::from_bits(val.into_f32()?)),
+        }
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> SerdeResult<V::Value> {
+        visitor.visit_f64(f64::from_bits(self.val.into_f64()?))
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> SerdeResult<V::Value> {
+        visitor.visit_borrowed_str(borrow_str(self.val.into_string()?)?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> SerdeResult<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> SerdeResult<V::Value> {
+        visitor.visit_borrowed_bytes(self.val.into_bytes()?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> SerdeResult<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> SerdeResult<V::Value> {
+        match self.val.into_option()? {
+            Some(v) => visitor.visit_some(Deserializer { val: v, ids: self.ids }).map_err(|e| e.at(PathSeg::Some)),
+            None => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> SerdeResult<V::Value> {
+        self.val.into_unit()?;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, name: &'static str, visitor: V) -> SerdeResult<V::Value> {
+        let _ = self.fields(name, 0)?;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, name: &'static str, visitor: V) -> SerdeResult<V::Value> {
+        let id = (self.ids)(name);
+        let v = self.val.into_alias_of(id)?;
+        visitor.visit_newtype_struct(Deserializer { val: v, ids: self.ids }).map_err(|e| e.at(PathSeg::Alias(id)))
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> SerdeResult<V::Value> {
+        match self.val {
+            Value::List(_, s) => visitor.visit_seq(Seq::new(self.ids, SeqKind::List, s)),
+            Value::Tuple(s) => visitor.visit_seq(Seq::new(self.ids, SeqKind::Tuple, s)),
+            Value::Bytes(b) => visitor.visit_seq(de::value::SeqDeserializer::new(b.iter().copied())),
+            val => Err(SchemaError::tag(Tag::List, val.as_tag()).into()),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> SerdeResult<V::Value> {
+        let s = self.val.into_tuple()?;
+        expect_len(len, s.len())?;
+        visitor.visit_seq(Seq::new(self.ids, SeqKind::Tuple, s))
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, name: &'static str, len: usize, visitor: V) -> SerdeResult<V::Value> {
+        visitor.visit_seq(self.fields(name, len)?)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> SerdeResult<V::Value> {
+        visitor.visit_map(Map::new(self.ids, self.val.into_map()?))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> SerdeResult<V::Value> {
+        visitor.visit_seq(self.fields(name, fields.len())?)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> SerdeResult<V::Value> {
+        self.val.expect_id((self.ids)(name))?;
+        let ids = self.ids;
+        match self.val {
+            Value::CEnum(id, ev) => visitor.visit_enum(Enum { de: Deserializer { val: Value::Unit, ids }, id, ev, unit: true }),
+            Value::Enum(id, ev, v) => visitor.visit_enum(Enum { de: Deserializer { val: *v, ids }, id, ev, unit: false }),
+            val => Err(SchemaError::tag(Tag::Enum, val.as_tag()).into()),
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> SerdeResult<V::Value> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> SerdeResult<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+struct Seq<'de> {
+    ids: fn(&'static str) -> TypeId,
+    kind: SeqKind,
+    iter: vec::IntoIter<Value<&'de [u8]>>,
+    index: usize,
+}
+
+impl<'de> Seq<'de> {
+    fn new(ids: fn(&'static str) -> TypeId, kind: SeqKind, s: Box<[Value<&'de [u8]>]>) -> Seq<'de> {
+        Seq { ids, kind, iter: s.into_vec().into_iter(), index: 0 }
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for Seq<'de> {
+    type Error = SerdeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> SerdeResult<Option<T::Value>> {
+        let Some(val) = self.iter.next() else {
+            return Ok(None);
+        };
+        let i = self.index;
+        self.index += 1;
+        seed.deserialize(Deserializer { val, ids: self.ids }).map(Some).map_err(|e| e.at(PathSeg::Index(self.kind, i)))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+type Entry<'de> = (Value<&'de [u8]>, Value<&'de [u8]>);
+
+struct Map<'de> {
+    ids: fn(&'static str) -> TypeId,
+    iter: vec::IntoIter<Entry<'de>>,
+    index: usize,
+    // of the last key
+    value: Option<Value<&'de [u8]>>,
+}
+
+impl<'de> Map<'de> {
+    fn new(ids: fn(&'static str) -> TypeId, s: Box<[Entry<'de>]>) -> Map<'de> {
+        Map { ids, iter: s.into_vec().into_iter(), index: 0, value: None }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Map<'de> {
+    type Error = SerdeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> SerdeResult<Option<K::Value>> {
+        let Some((k, v)) = self.iter.next() else {
+            return Ok(None);
+        };
+        self.index += 1;
+        self.value = Some(v);
+        let i = self.index - 1;
+        seed.deserialize(Deserializer { val: k, ids: self.ids }).map(Some).map_err(|e| e.at(PathSeg::Key(i)))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> SerdeResult<V::Value> {
+        let val = self.value.take().ok_or_else(|| <SerdeError as de::Error>::custom("value without key"))?;
+        let i = self.index - 1;
+        seed.deserialize(Deserializer { val, ids: self.ids }).map_err(|e| e.at(PathSeg::Value(i)))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct Enum<'de> {
+    // of the payload
+    de: Deserializer<'de>,
+    id: TypeId,
+    ev: EnumVariantId,
+    // from a `CEnum`
+    unit: bool,
+}
+
+impl<'de> de::EnumAccess<'de> for Enum<'de> {
+    type Error = SerdeError;
+    type Variant = Enum<'de>;
+
+    fn variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> SerdeResult<(T::Value, Enum<'de>)> {
+        let de: de::value::U64Deserializer<SerdeError> = self.ev.into_deserializer();
+        Ok((seed.deserialize(de)?, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Enum<'de> {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> SerdeResult<()> {
+        if !self.unit {
+            self.de.val.into_unit().map_err(|e| SerdeError::from(e).at(PathSeg::Variant(self.id, self.ev)))?;
+        }
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> SerdeResult<T::Value> {
+        self.expect_payload()?;
+        seed.deserialize(self.de).map_err(|e| e.at(PathSeg::Variant(self.id, self.ev)))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> SerdeResult<V::Value> {
+        self.expect_payload()?;
+        let (id, ev) = (self.id, self.ev);
+        de::Deserializer::deserialize_tuple(self.de, len, visitor).map_err(|e| e.at(PathSeg::Variant(id, ev)))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> SerdeResult<V::Value> {
+        self.expect_payload()?;
+        let (id, ev) = (self.id, self.ev);
+        (|| {
+            self.de.val.expect_id(TypeId::Anonymous)?;
+            let s = self.de.val.into_struct()?;
+            expect_len(fields.len(), s.len())?;
+            visitor.visit_seq(Seq::new(self.de.ids, SeqKind::Struct(TypeId::Anonymous), s))
+        })().map_err(|e| e.at(PathSeg::Variant(id, ev)))
+    }
+}
+
+impl<'de> Enum<'de> {
+    // unit variants from `CEnum`s have no payload to deserialize anything else from
+    fn expect_payload(&self) -> SerdeResult<()> {
+        if self.unit {
+            return Err(SchemaError::tag(Tag::Enum, Tag::CEnum).into());
+        }
+        Ok(())
+    }
+}
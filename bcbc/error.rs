@@ -0,0 +1,240 @@
+use core::fmt;
+use super::*;
+
+struct Hex<'a>(&'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, b) in self.0.iter().enumerate() {
+            if i != 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+// where an extvar is, by the L4 of its header
+fn extvar_place(l4: L4) -> &'static str {
+    match l4 {
+        EXT8 => "1 byte",
+        EXT16 => "2 bytes",
+        EXT32 => "4 bytes",
+        EXT64 => "8 bytes",
+        _ => "the header",
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::TooLongLen(len) => write!(f, "length {} exceeds the limit", len),
+            Error::TooManyItems(items) => write!(f, "{} items exceed the limit", items),
+            Error::TooDeep(depth) => write!(f, "depth {} exceeds the limit", depth),
+            Error::TooLongInput(len) => write!(f, "input of {} bytes exceeds the limit", len),
+            Error::Tag(tag) => write!(f, "invalid type tag 0x{:02x}", tag),
+            Error::BytevarIntSign { buf } => write!(f, "bytevar {} out of range of the signed type", Hex(buf)),
+            Error::BytevarLongerThanType { len, nlen, buf } => {
+                write!(f, "bytevar {} of {} bytes longer than the type of {} bytes", Hex(buf), len, nlen)
+            }
+            Error::BytevarLongerThanExpected { len, exp_len, buf, .. } => {
+                write!(f, "bytevar {} of {} bytes, longer than the needed {}", Hex(buf), len, exp_len)
+            }
+            Error::BytevarNegZero { buf } => write!(f, "bytevar {} is negative zero", Hex(buf)),
+            Error::ExtvarTooLong { l4, exp_l4, u } => {
+                write!(f, "extvar {} in {} where {} suffices", u, extvar_place(*l4), extvar_place(*exp_l4))
+            }
+            Error::Ext2(ext2) => write!(f, "reserved Ext2 0x{:x}", ext2),
+            Error::Char(u) => write!(f, "invalid char 0x{:x}", u),
+            // also the sources, which are not reachable without `std`
+            Error::Utf8(e) => write!(f, "invalid UTF-8 string: {}", e),
+            Error::Read(e) => write!(f, "read error: {}", e),
+            Error::Type(e) => write!(f, "type error: {}", e),
+            Error::Fatal(e) => write!(f, "fatal error: {}", e),
+        }
+    }
+}
+
+impl fmt::Display for Fatal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fatal::H4(n) => write!(f, "invalid H4 0x{:x}", n),
+            Fatal::L4(n) => write!(f, "invalid L4 0x{:x}", n),
+            Fatal::Ext1(n) => write!(f, "invalid Ext1 0x{:x}", n),
+            Fatal::H4ToN(h4) => write!(f, "H4::{:?} is not a bytevar length", h4),
+            Fatal::NToH4(n) => write!(f, "no H4 for a bytevar of {} bytes", n),
+            Fatal::H4ToExt1(h4) => write!(f, "H4::{:?} is not an Ext1", h4),
+            Fatal::H4ToExt2(h4) => write!(f, "H4::{:?} is not an Ext2", h4),
+            Fatal::ToSize(u) => write!(f, "{} does not fit in usize", u),
+            Fatal::FromSize(n) => write!(f, "{} does not fit in u64", n),
+            Fatal::BytevarSlicing => f.write_str("bytevar slicing out of range"),
+            Fatal::UnexpectedEnd => f.write_str("unexpected end of a container"),
+        }
+    }
+}
+
+impl fmt::Display for TypeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeErrorKind::Mismatch { expected, found } => write!(f, "expected {}, found {}", expected, found),
+            TypeErrorKind::TupleMismatch { expected, len } => write!(f, "expected {}, found a tuple of {} items", expected, len),
+            TypeErrorKind::UnknownVariant { id, ev } => write!(f, "unknown variant {} of {}", ev, id),
+            TypeErrorKind::FieldCount { id, expected, found } => write!(f, "expected {} fields of {}, found {}", expected, id, found),
+        }
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.kind, self.path)
+    }
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::TooLongLen(len) => write!(f, "length {} exceeds the limit", len),
+            EncodeError::TooManyItems(items) => write!(f, "{} items exceed the limit", items),
+            EncodeError::TooDeep(depth) => write!(f, "depth {} exceeds the limit", depth),
+            EncodeError::TooLongOutput(len) => write!(f, "output of {} bytes exceeds the limit", len),
+            EncodeError::TooLongTuple(len) => write!(f, "tuple type of {} elements, more than 255", len),
+            EncodeError::LenMismatch { len, written } => write!(f, "{} items written into a container of {}", written, len),
+            EncodeError::UnexpectedEnd => f.write_str("end without an open container"),
+            EncodeError::Unfinished { depth } => write!(f, "finished with {} open containers", depth),
+            // also the sources, like `Error`
+            EncodeError::Write(e) => write!(f, "write error: {}", e),
+            EncodeError::Type(e) => write!(f, "type error: {}", e),
+            EncodeError::Fatal(e) => write!(f, "fatal error: {}", e),
+        }
+    }
+}
+
+impl fmt::Display for SchemaErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaErrorKind::Tag { expected, found } => write!(f, "expected {}, found {}", expected.name(), found.name()),
+            SchemaErrorKind::TypeId { expected, found } => write!(f, "expected id {}, found {}", expected, found),
+            SchemaErrorKind::Variant { id, ev } => write!(f, "unknown variant {} of {}", ev, id),
+            SchemaErrorKind::Len { expected, found } => write!(f, "expected {} items, found {}", expected, found),
+        }
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.kind, self.path)
+    }
+}
+
+impl<B: AsRef<[u8]>> FullError<B> {
+    // with a copy of the input, e.g. to return a `FullError<&[u8]>` as `Box<dyn Error>`
+    pub fn into_owned(self) -> FullError<Vec<u8>> {
+        FullError { err: self.err, buf: self.buf.as_ref().to_vec(), pos: self.pos, path: self.path }
+    }
+}
+
+// with up to 8 bytes on both sides of the position, like `at byte 3 in root, near 11 00 01 | 02`
+impl<B: AsRef<[u8]>> fmt::Display for FullError<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let buf = self.buf.as_ref();
+        let pos = self.pos.min(buf.len());
+        let start = pos.saturating_sub(8);
+        let end = buf.len().min(pos + 8);
+        write!(f, "{} at byte {} in {}, near ", self.err, self.pos, self.path)?;
+        if start != 0 {
+            f.write_str(".. ")?;
+        }
+        write!(f, "{} |", Hex(&buf[start..pos]))?;
+        if pos != end {
+            write!(f, " {}", Hex(&buf[pos..end]))?;
+        }
+        if end != buf.len() {
+            f.write_str(" ..")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Skipped => "skipped",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {} at byte {} in {}", severity, self.err, self.pos, self.path)
+    }
+}
+
+#[cfg(feature = "json")]
+impl fmt::Display for JsonErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonErrorKind::Expected(what) => write!(f, "expected {}", what),
+            JsonErrorKind::OutOfRange => f.write_str("number out of range"),
+            JsonErrorKind::Hex => f.write_str("invalid hex"),
+            JsonErrorKind::Len { expected, found } => write!(f, "expected {} items, found {}", expected, found),
+            JsonErrorKind::Tag(tag) => write!(f, "unknown type tag {}", tag),
+            JsonErrorKind::Type(t) => write!(f, "unknown type {}", t),
+            JsonErrorKind::Variant(name) => write!(f, "unknown variant {}", name),
+            JsonErrorKind::Field(name) => write!(f, "unknown field {}", name),
+            JsonErrorKind::MissingField(name) => write!(f, "missing field {}", name),
+            JsonErrorKind::Serde(msg) => f.write_str(msg),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.kind, self.path)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Utf8(e) => Some(e),
+            Error::Read(e) => Some(e),
+            Error::Type(e) => Some(e),
+            Error::Fatal(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EncodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EncodeError::Write(e) => Some(e),
+            EncodeError::Type(e) => Some(e),
+            EncodeError::Fatal(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Fatal {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TypeError {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SchemaError {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseTypeError {}
+
+#[cfg(feature = "std")]
+impl<B: AsRef<[u8]> + fmt::Debug> std::error::Error for FullError<B> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.err)
+    }
+}
+
+#[cfg(all(feature = "std", feature = "json"))]
+impl std::error::Error for JsonError {}
@@ -0,0 +1,181 @@
+use core::{fmt, ops::Range};
+use alloc::string::String;
+use super::*;
+
+// one part of an encoding, see `Value::explain`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Note {
+    pub range: Range<usize>,
+    // of the value, its type annotation is one deeper
+    pub depth: usize,
+    pub kind: NoteKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NoteKind {
+    Header(H4, L4),
+    // length or variant id following the header
+    Ext(u64),
+    Tag(Tag),
+    TupleSize(u8),
+    TypeId(TypeId),
+    // in the text notation
+    Payload(String),
+    // covers the bytes read since the last note, which may be none
+    Error(Error),
+}
+
+// where a value is in the input
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Span {
+    // with inner values
+    pub range: Range<usize>,
+    // with extvar
+    pub header: Range<usize>,
+    // annotations, both of a map, or the payload of a `Type` value
+    pub ty: Option<Range<usize>>,
+    // of an alias, enum, cenum or struct, or the payload of a `TypeId` value
+    pub type_id: Option<Range<usize>>,
+    // in order, with keys and values of a map interleaved
+    pub inner: Vec<Span>,
+}
+
+// pops the innermost open value into its parent
+fn close(stack: &mut Vec<(usize, Span)>) {
+    let (_, span) = stack.pop().unwrap();
+    let parent = &mut stack.last_mut().unwrap().1;
+    parent.range.end = span.range.end;
+    parent.inner.push(span);
+}
+
+fn extend(range: &mut Option<Range<usize>>, with: &Range<usize>) {
+    match range {
+        Some(range) => range.end = with.end,
+        None => *range = Some(with.clone()),
+    }
+}
+
+impl Span {
+    // notes of a value are at one deeper than its header and precede its inner values, whose headers are also one
+    // deeper, so a value ends before the next header not deeper than its own
+    pub(crate) fn from_notes(notes: &[Note]) -> Span {
+        // open values with their depths, the first holds the top-level value
+        let mut stack = vec![(0, Span::default())];
+        let mut last_tag = false;
+        for note in notes {
+            if let NoteKind::Header(..) = note.kind {
+                while stack.len() > 1 && stack.last().unwrap().0 >= note.depth {
+                    close(&mut stack);
+                }
+                let range = note.range.clone();
+                stack.push((note.depth, Span { range: range.clone(), header: range, ..Span::default() }));
+                last_tag = false;
+                continue;
+            }
+            let span = &mut stack.last_mut().unwrap().1;
+            span.range.end = note.range.end;
+            match note.kind {
+                NoteKind::Ext(_) => span.header.end = note.range.end,
+                NoteKind::Tag(_) | NoteKind::TupleSize(_) => extend(&mut span.ty, &note.range),
+                // of an alias, enum, cenum or struct type
+                NoteKind::TypeId(_) if last_tag => extend(&mut span.ty, &note.range),
+                NoteKind::TypeId(_) => span.type_id = Some(note.range.clone()),
+                _ => {}
+            }
+            last_tag = matches!(note.kind, NoteKind::Tag(_));
+        }
+        while stack.len() > 1 {
+            close(&mut stack);
+        }
+        stack.pop().unwrap().1.inner.pop().unwrap_or_default()
+    }
+
+    // of the value at the path, or of its type annotation if the path ends at one, None if the path leads nowhere
+    pub fn range_at(&self, path: &Path) -> Option<Range<usize>> {
+        let mut span = self;
+        for seg in path.segs() {
+            let i = match *seg {
+                PathSeg::Some | PathSeg::Alias(_) | PathSeg::Variant(..) => 0,
+                PathSeg::Index(_, i) => i,
+                PathSeg::Key(i) => i * 2,
+                PathSeg::Value(i) => i * 2 + 1,
+                PathSeg::Type => return span.ty.clone(),
+            };
+            span = span.inner.get(i)?;
+        }
+        Some(span.range.clone())
+    }
+}
+
+// notes cover the input from the start up to the end, or the failure
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Explanation<B> {
+    pub buf: B,
+    pub notes: Vec<Note>,
+    // None if failed
+    pub value: Option<Value<B>>,
+}
+
+fn ext_name(l4: L4) -> Option<&'static str> {
+    match l4 {
+        EXT8 => Some("ext8"),
+        EXT16 => Some("ext16"),
+        EXT32 => Some("ext32"),
+        EXT64 => Some("ext64"),
+        _ => None,
+    }
+}
+
+impl fmt::Display for NoteKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NoteKind::Header(h4, l4) => {
+                write!(f, "H4::{:?} ", h4)?;
+                let name = match h4 {
+                    H4::String | H4::Bytes | H4::List | H4::Map | H4::Tuple | H4::Struct => "len",
+                    H4::CEnum | H4::Enum => "ev",
+                    _ => return match l4 {
+                        L4::EXT1 => match h4.to_ext1() {
+                            Ok(ext1) => write!(f, "L4::EXT1 Ext1::{:?}", ext1),
+                            Err(_) => write!(f, "L4::EXT1"),
+                        },
+                        L4::EXT2 => match h4.to_ext2() {
+                            Ok(ext2) => write!(f, "L4::EXT2 Ext2::{:?}", ext2),
+                            Err(_) => write!(f, "L4::EXT2 Ext2::?"),
+                        },
+                        l4 => write!(f, "L4::{:?}", l4),
+                    },
+                };
+                match ext_name(*l4) {
+                    Some(ext) => write!(f, "{}={}", name, ext),
+                    None => write!(f, "{}={}", name, *l4 as u8),
+                }
+            }
+            NoteKind::Ext(u) => write!(f, "extvar {}", u),
+            NoteKind::Tag(tag) => write!(f, "Tag::{:?}", tag),
+            NoteKind::TupleSize(size) => write!(f, "size={}", size),
+            NoteKind::TypeId(id) => write!(f, "TypeId {}", id),
+            NoteKind::Payload(s) => f.write_str(s),
+            NoteKind::Error(err) => write!(f, "error: {}", err),
+        }
+    }
+}
+
+// one line per note, like `0001..0002    06 → Tag::U64`, with at most 16 bytes shown
+impl<B: AsRef<[u8]>> fmt::Display for Explanation<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let buf = self.buf.as_ref();
+        for note in &self.notes {
+            write!(f, "{:04x}..{:04x}  {:w$}", note.range.start, note.range.end, "", w = note.depth * 2)?;
+            let bytes = &buf[note.range.clone()];
+            for b in bytes.iter().take(16) {
+                write!(f, "{:02x} ", b)?;
+            }
+            if bytes.len() > 16 {
+                write!(f, ".. ({} bytes) ", bytes.len())?;
+            }
+            writeln!(f, "→ {}", note.kind)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,483 @@
+// Two mappings between `Value` and JSON.
+//
+// Lossless, with `to_json` and `from_json`:
+// - `Unit`, `Bool` and `String` are `null`, booleans and strings, `Tuple`s are arrays
+// - everything else is an object with the variant name as the only key:
+//   `{"U8":1}`, `{"U128":"1"}`, `{"F32":1065353216}` (bits), `{"Char":"a"}`, `{"Bytes":"0102"}` (hex),
+//   `{"Option":["U8"]}` for `None` and `{"Option":["U8",{"U8":1}]}` for `Some`,
+//   `{"List":[type,[..]]}`, `{"Map":[[tk,tv],[[k,v],..]]}`, `{"Alias":[id,v]}`, `{"CEnum":[id,ev]}`,
+//   `{"Enum":[id,ev,v]}`, `{"Struct":[id,[..]]}`, `{"Type":type}` and `{"TypeId":id}`
+// - types and ids as with their serde impls
+//
+// With a registry, with `to_json_with` and `from_json_with`, which needs the type to read as:
+// - numbers as numbers, floats from their bits, except NaN and infinities as `"NaN"`, `"inf"` and `"-inf"`,
+//   and 128-bit integers as strings when out of the range of 64-bit ones
+// - `Char`s as strings, `Bytes` as hex strings
+// - `None` as `null` and `Some` as the inner value, so `Option<Option<T>>` and `Option<Unit>` are ambiguous
+// - `List`s and `Tuple`s as arrays, `Map`s with string keys as objects and as arrays of pairs otherwise
+// - `Alias`es as the inner value
+// - `CEnum`s as the variant name, `Enum`s as `{"Name":payload}` or just `"Name"` for `Unit` payloads,
+//   with the variant id as name for unknown variants
+// - `Struct`s as objects of the fields, or arrays if not in the registry
+// - `Type`s and `TypeId`s as in the lossless mapping
+//
+// Both are recursive, like `serde_json::Value` itself.
+
+use alloc::{format, string::{String, ToString}, vec::Vec};
+use serde_json::{Map, Number, Value as Json};
+use super::*;
+
+impl JsonError {
+    pub const fn new(kind: JsonErrorKind) -> JsonError {
+        JsonError { path: Path::new(), kind }
+    }
+
+    pub fn at(mut self, seg: PathSeg) -> JsonError {
+        self.path.0.insert(0, seg);
+        self
+    }
+}
+
+fn err<T>(kind: JsonErrorKind) -> JsonResult<T> {
+    Err(JsonError::new(kind))
+}
+
+fn expected<T>(what: &'static str) -> JsonResult<T> {
+    err(JsonErrorKind::Expected(what))
+}
+
+const HEX: &[u8; 16] = b"0123456789abcdef";
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        s.push(HEX[(b >> 4) as usize] as char);
+        s.push(HEX[(b & 0xf) as usize] as char);
+    }
+    s
+}
+
+fn from_hex(s: &str) -> JsonResult<Vec<u8>> {
+    fn digit(c: u8) -> JsonResult<u8> {
+        match (c as char).to_digit(16) {
+            Some(d) => Ok(d as u8),
+            None => err(JsonErrorKind::Hex),
+        }
+    }
+    let s = s.as_bytes();
+    if !s.len().is_multiple_of(2) {
+        return err(JsonErrorKind::Hex);
+    }
+    s.chunks(2).map(|c| Ok((digit(c[0])? << 4) | digit(c[1])?)).collect()
+}
+
+fn tagged(tag: &str, v: Json) -> Json {
+    let mut map = Map::new();
+    let _ = map.insert(tag.to_string(), v);
+    Json::Object(map)
+}
+
+fn type_to_json(t: &Type) -> Json {
+    serde_json::to_value(t).unwrap()
+}
+
+fn type_id_to_json(id: &TypeId) -> Json {
+    serde_json::to_value(id).unwrap()
+}
+
+fn serde_from_json<T: serde::de::DeserializeOwned>(j: &Json) -> JsonResult<T> {
+    T::deserialize(j).map_err(|e| JsonError::new(JsonErrorKind::Serde(e.to_string())))
+}
+
+fn float_to_json(f: f64) -> Json {
+    match Number::from_f64(f) {
+        Some(n) => Json::Number(n),
+        None if f.is_nan() => Json::from("NaN"),
+        None if f > 0.0 => Json::from("inf"),
+        None => Json::from("-inf"),
+    }
+}
+
+fn float_from_json(j: &Json) -> JsonResult<f64> {
+    match j {
+        Json::Number(n) => n.as_f64().map_or_else(|| expected("number"), Ok),
+        Json::String(s) if s == "NaN" => Ok(f64::NAN),
+        Json::String(s) if s == "inf" => Ok(f64::INFINITY),
+        Json::String(s) if s == "-inf" => Ok(f64::NEG_INFINITY),
+        _ => expected("number"),
+    }
+}
+
+// shortest representation of the `f32` instead of the exact value
+fn f32_to_json(f: f32) -> Json {
+    float_to_json(format!("{}", f).parse().unwrap_or(f64::from(f)))
+}
+
+fn u64_from_json(j: &Json) -> JsonResult<u64> {
+    j.as_u64().map_or_else(|| expected("unsigned integer"), Ok)
+}
+
+fn i64_from_json(j: &Json) -> JsonResult<i64> {
+    j.as_i64().map_or_else(|| expected("integer"), Ok)
+}
+
+fn str_from_json(j: &Json) -> JsonResult<&str> {
+    j.as_str().map_or_else(|| expected("string"), Ok)
+}
+
+fn array_from_json(j: &Json) -> JsonResult<&[Json]> {
+    j.as_array().map_or_else(|| expected("array"), |a| Ok(a.as_slice()))
+}
+
+fn array_of<const N: usize>(j: &Json) -> JsonResult<&[Json; N]> {
+    let a = array_from_json(j)?;
+    a.try_into().map_err(|_| JsonError::new(JsonErrorKind::Len { expected: N, found: a.len() }))
+}
+
+fn range<T: TryFrom<u64> + TryFrom<i64>>(j: &Json, signed: bool) -> JsonResult<T> {
+    let n = if signed { T::try_from(i64_from_json(j)?).ok() } else { T::try_from(u64_from_json(j)?).ok() };
+    n.map_or_else(|| err(JsonErrorKind::OutOfRange), Ok)
+}
+
+fn u128_from_json(j: &Json) -> JsonResult<u128> {
+    match j {
+        Json::String(s) => s.parse().map_err(|_| JsonError::new(JsonErrorKind::OutOfRange)),
+        j => u64_from_json(j).map(u128::from),
+    }
+}
+
+fn i128_from_json(j: &Json) -> JsonResult<i128> {
+    match j {
+        Json::String(s) => s.parse().map_err(|_| JsonError::new(JsonErrorKind::OutOfRange)),
+        j => i64_from_json(j).map(i128::from),
+    }
+}
+
+fn char_from_json(j: &Json) -> JsonResult<char> {
+    let mut chars = str_from_json(j)?.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => expected("string of a single char"),
+    }
+}
+
+fn string(s: &str) -> Value<Vec<u8>> {
+    Value::String(String::from(s).into())
+}
+
+// prepends the index of the failed item to errors
+fn seq_from_json<'a>(
+    kind: SeqKind,
+    items: impl Iterator<Item = &'a Json>,
+    mut f: impl FnMut(usize, &'a Json) -> JsonResult<Value<Vec<u8>>>,
+) -> JsonResult<Box<[Value<Vec<u8>>]>> {
+    items.enumerate().map(|(i, j)| f(i, j).map_err(|e| e.at(PathSeg::Index(kind, i)))).collect()
+}
+
+impl<B: AsRef<[u8]> + ByteStorage> Value<B> {
+    pub fn to_json(&self) -> Json {
+        let seq = |s: &[Value<B>]| Json::Array(s.iter().map(Value::to_json).collect());
+        match self {
+            Value::Unit => Json::Null,
+            Value::Bool(v) => Json::Bool(*v),
+            Value::U8(v) => tagged("U8", Json::from(*v)),
+            Value::U16(v) => tagged("U16", Json::from(*v)),
+            Value::U32(v) => tagged("U32", Json::from(*v)),
+            Value::U64(v) => tagged("U64", Json::from(*v)),
+            Value::I8(v) => tagged("I8", Json::from(*v)),
+            Value::I16(v) => tagged("I16", Json::from(*v)),
+            Value::I32(v) => tagged("I32", Json::from(*v)),
+            Value::I64(v) => tagged("I64", Json::from(*v)),
+            Value::F16(v) => tagged("F16", Json::from(*v)),
+            Value::F32(v) => tagged("F32", Json::from(*v)),
+            Value::F64(v) => tagged("F64", Json::from(*v)),
+            Value::Char(v) => tagged("Char", Json::from(v.to_string())),
+            Value::U128(v) => tagged("U128", Json::from(v.to_string())),
+            Value::I128(v) => tagged("I128", Json::from(v.to_string())),
+            Value::String(s) => Json::from(AsRef::<str>::as_ref(s)),
+            Value::Bytes(b) => tagged("Bytes", Json::from(to_hex(b.as_ref()))),
+            Value::Option(t, v) => {
+                let mut a = vec![type_to_json(t)];
+                a.extend(v.as_ref().as_ref().map(Value::to_json));
+                tagged("Option", Json::Array(a))
+            }
+            Value::List(t, s) => tagged("List", Json::Array(vec![type_to_json(t), seq(s)])),
+            Value::Map((tk, tv), s) => {
+                let entries = s.iter().map(|(k, v)| Json::Array(vec![k.to_json(), v.to_json()])).collect();
+                let types = Json::Array(vec![type_to_json(tk), type_to_json(tv)]);
+                tagged("Map", Json::Array(vec![types, Json::Array(entries)]))
+            }
+            Value::Tuple(s) => seq(s),
+            Value::Alias(id, v) => tagged("Alias", Json::Array(vec![type_id_to_json(id), v.to_json()])),
+            Value::CEnum(id, ev) => tagged("CEnum", Json::Array(vec![type_id_to_json(id), Json::from(*ev)])),
+            Value::Enum(id, ev, v) => tagged("Enum", Json::Array(vec![type_id_to_json(id), Json::from(*ev), v.to_json()])),
+            Value::Struct(id, s) => tagged("Struct", Json::Array(vec![type_id_to_json(id), seq(s)])),
+            Value::Type(t) => tagged("Type", type_to_json(t)),
+            Value::TypeId(id) => tagged("TypeId", type_id_to_json(id)),
+        }
+    }
+
+    pub fn to_json_with(&self, reg: &Registry) -> Json {
+        let seq = |s: &[Value<B>]| Json::Array(s.iter().map(|v| v.to_json_with(reg)).collect());
+        match self {
+            Value::Unit => Json::Null,
+            Value::Bool(v) => Json::Bool(*v),
+            Value::U8(v) => Json::from(*v),
+            Value::U16(v) => Json::from(*v),
+            Value::U32(v) => Json::from(*v),
+            Value::U64(v) => Json::from(*v),
+            Value::I8(v) => Json::from(*v),
+            Value::I16(v) => Json::from(*v),
+            Value::I32(v) => Json::from(*v),
+            Value::I64(v) => Json::from(*v),
+            Value::F16(v) => f32_to_json(casting::f16_to_f32(*v)),
+            Value::F32(v) => f32_to_json(f32::from_bits(*v)),
+            Value::F64(v) => float_to_json(f64::from_bits(*v)),
+            Value::Char(v) => Json::from(v.to_string()),
+            Value::U128(v) => u64::try_from(*v).map_or_else(|_| Json::from(v.to_string()), Json::from),
+            Value::I128(v) => i64::try_from(*v).map_or_else(|_| Json::from(v.to_string()), Json::from),
+            Value::String(s) => Json::from(AsRef::<str>::as_ref(s)),
+            Value::Bytes(b) => Json::from(to_hex(b.as_ref())),
+            Value::Option(_, v) => v.as_ref().as_ref().map_or(Json::Null, |v| v.to_json_with(reg)),
+            Value::List(_, s) | Value::Tuple(s) => seq(s),
+            Value::Map(_, s) if s.iter().all(|(k, _)| matches!(k, Value::String(_))) => {
+                Json::Object(s.iter().map(|(k, v)| {
+                    let Value::String(k) = k else { unreachable!() };
+                    (String::from(AsRef::<str>::as_ref(k)), v.to_json_with(reg))
+                }).collect())
+            }
+            Value::Map(_, s) => {
+                Json::Array(s.iter().map(|(k, v)| Json::Array(vec![k.to_json_with(reg), v.to_json_with(reg)])).collect())
+            }
+            Value::Alias(_, v) => v.to_json_with(reg),
+            Value::CEnum(id, ev) => match reg.c_variant(id, *ev) {
+                Some(variant) => Json::from(variant.name.as_str()),
+                None => Json::from(*ev),
+            },
+            Value::Enum(id, ev, v) => {
+                let name = reg.variant(id, *ev).map_or_else(|| ev.to_string(), |variant| variant.name.clone());
+                match v.as_ref() {
+                    Value::Unit => Json::from(name),
+                    v => tagged(&name, v.to_json_with(reg)),
+                }
+            }
+            Value::Struct(id, s) => match reg.fields(id) {
+                Some(fields) if fields.len() == s.len() => {
+                    Json::Object(fields.iter().zip(s.iter()).map(|(f, v)| (f.name.clone(), v.to_json_with(reg))).collect())
+                }
+                _ => seq(s),
+            },
+            Value::Type(t) => tagged("Type", type_to_json(t)),
+            Value::TypeId(id) => tagged("TypeId", type_id_to_json(id)),
+        }
+    }
+}
+
+impl Value<Vec<u8>> {
+    pub fn from_json(j: &Json) -> JsonResult<Value<Vec<u8>>> {
+        let map = match j {
+            Json::Null => return Ok(Value::Unit),
+            Json::Bool(v) => return Ok(Value::Bool(*v)),
+            Json::String(s) => return Ok(string(s)),
+            Json::Array(a) => return Ok(Value::Tuple(seq_from_json(SeqKind::Tuple, a.iter(), |_, j| Value::from_json(j))?)),
+            Json::Number(_) => return expected("tagged object"),
+            Json::Object(map) => map,
+        };
+        let mut iter = map.iter();
+        let (Some((tag, v)), None) = (iter.next(), iter.next()) else {
+            return expected("object with a single key");
+        };
+        Ok(match tag.as_str() {
+            "U8" => Value::U8(range(v, false)?),
+            "U16" => Value::U16(range(v, false)?),
+            "U32" => Value::U32(range(v, false)?),
+            "U64" => Value::U64(u64_from_json(v)?),
+            "I8" => Value::I8(range(v, true)?),
+            "I16" => Value::I16(range(v, true)?),
+            "I32" => Value::I32(range(v, true)?),
+            "I64" => Value::I64(i64_from_json(v)?),
+            "F16" => Value::F16(range(v, false)?),
+            "F32" => Value::F32(range(v, false)?),
+            "F64" => Value::F64(u64_from_json(v)?),
+            "Char" => Value::Char(char_from_json(v)?),
+            "U128" => Value::U128(u128_from_json(v)?),
+            "I128" => Value::I128(i128_from_json(v)?),
+            "Bytes" => Value::Bytes(from_hex(str_from_json(v)?)?),
+            "Option" => match array_from_json(v)? {
+                [t] => Value::Option(serde_from_json(t)?, Box::new(None)),
+                [t, v] => {
+                    let v = Value::from_json(v).map_err(|e| e.at(PathSeg::Some))?;
+                    Value::Option(serde_from_json(t)?, Box::new(Some(v)))
+                }
+                a => return err(JsonErrorKind::Len { expected: 2, found: a.len() }),
+            },
+            "List" => {
+                let [t, s] = array_of(v)?;
+                Value::List(serde_from_json(t)?, seq_from_json(SeqKind::List, array_from_json(s)?.iter(), |_, j| Value::from_json(j))?)
+            }
+            "Map" => {
+                let [types, s] = array_of(v)?;
+                let [tk, tv] = array_of(types)?;
+                let s = array_from_json(s)?.iter().enumerate().map(|(i, j)| {
+                    let [k, v] = array_of(j)?;
+                    Ok((
+                        Value::from_json(k).map_err(|e| e.at(PathSeg::Key(i)))?,
+                        Value::from_json(v).map_err(|e| e.at(PathSeg::Value(i)))?,
+                    ))
+                }).collect::<JsonResult<_>>()?;
+                Value::Map((serde_from_json(tk)?, serde_from_json(tv)?), s)
+            }
+            "Alias" => {
+                let [id, v] = array_of(v)?;
+                let id = serde_from_json(id)?;
+                Value::Alias(id, Box::new(Value::from_json(v).map_err(|e| e.at(PathSeg::Alias(id)))?))
+            }
+            "CEnum" => {
+                let [id, ev] = array_of(v)?;
+                Value::CEnum(serde_from_json(id)?, u64_from_json(ev)?)
+            }
+            "Enum" => {
+                let [id, ev, v] = array_of(v)?;
+                let id = serde_from_json(id)?;
+                let ev = u64_from_json(ev)?;
+                let v = Value::from_json(v).map_err(|e| e.at(PathSeg::Variant(id, ev)))?;
+                Value::Enum(id, ev, Box::new(v))
+            }
+            "Struct" => {
+                let [id, s] = array_of(v)?;
+                let id = serde_from_json(id)?;
+                Value::Struct(id, seq_from_json(SeqKind::Struct(id), array_from_json(s)?.iter(), |_, j| Value::from_json(j))?)
+            }
+            "Type" => Value::Type(serde_from_json(v)?),
+            "TypeId" => Value::TypeId(serde_from_json(v)?),
+            tag => return err(JsonErrorKind::Tag(tag.to_string())),
+        })
+    }
+
+    pub fn from_json_with(j: &Json, t: &Type, reg: &Registry) -> JsonResult<Value<Vec<u8>>> {
+        let unknown = || err(JsonErrorKind::Type(t.clone()));
+        Ok(match t {
+            Type::Unknown => return unknown(),
+            Type::Unit => match j {
+                Json::Null => Value::Unit,
+                _ => return expected("null"),
+            },
+            Type::Bool => Value::Bool(j.as_bool().map_or_else(|| expected("boolean"), Ok)?),
+            Type::U8 => Value::U8(range(j, false)?),
+            Type::U16 => Value::U16(range(j, false)?),
+            Type::U32 => Value::U32(range(j, false)?),
+            Type::U64 => Value::U64(u64_from_json(j)?),
+            Type::I8 => Value::I8(range(j, true)?),
+            Type::I16 => Value::I16(range(j, true)?),
+            Type::I32 => Value::I32(range(j, true)?),
+            Type::I64 => Value::I64(i64_from_json(j)?),
+            Type::F16 => Value::F16(casting::f64_to_f16(float_from_json(j)?)),
+            Type::F32 => Value::F32((float_from_json(j)? as f32).to_bits()),
+            Type::F64 => Value::F64(float_from_json(j)?.to_bits()),
+            Type::Char => Value::Char(char_from_json(j)?),
+            Type::U128 => Value::U128(u128_from_json(j)?),
+            Type::I128 => Value::I128(i128_from_json(j)?),
+            Type::String => string(str_from_json(j)?),
+            Type::Bytes => Value::Bytes(from_hex(str_from_json(j)?)?),
+            Type::Option(t) => match j {
+                Json::Null => Value::Option((**t).clone(), Box::new(None)),
+                j => {
+                    let v = Value::from_json_with(j, t, reg).map_err(|e| e.at(PathSeg::Some))?;
+                    Value::Option((**t).clone(), Box::new(Some(v)))
+                }
+            },
+            Type::List(t) => {
+                let s = seq_from_json(SeqKind::List, array_from_json(j)?.iter(), |_, j| Value::from_json_with(j, t, reg))?;
+                Value::List((**t).clone(), s)
+            }
+            Type::Map(tk, tv) => {
+                let entry = |i: usize, k: &Json, v: &Json| -> JsonResult<_> { Ok((
+                    Value::from_json_with(k, tk, reg).map_err(|e| e.at(PathSeg::Key(i)))?,
+                    Value::from_json_with(v, tv, reg).map_err(|e| e.at(PathSeg::Value(i)))?,
+                )) };
+                let s: JsonResult<_> = match j {
+                    Json::Object(map) => map.iter().enumerate().map(|(i, (k, v))| entry(i, &Json::from(k.as_str()), v)).collect(),
+                    j => array_from_json(j)?.iter().enumerate().map(|(i, j)| {
+                        let [k, v] = array_of(j)?;
+                        entry(i, k, v)
+                    }).collect(),
+                };
+                Value::Map(((**tk).clone(), (**tv).clone()), s?)
+            }
+            Type::Tuple(ts) => {
+                let a = array_from_json(j)?;
+                if a.len() != ts.len() {
+                    return err(JsonErrorKind::Len { expected: ts.len(), found: a.len() });
+                }
+                Value::Tuple(seq_from_json(SeqKind::Tuple, a.iter(), |i, j| Value::from_json_with(j, &ts[i], reg))?)
+            }
+            Type::Alias(id) => {
+                let Some(t) = reg.alias(id) else { return unknown() };
+                Value::Alias(*id, Box::new(Value::from_json_with(j, t, reg).map_err(|e| e.at(PathSeg::Alias(*id)))?))
+            }
+            Type::CEnum(id) => {
+                let Some(Def::CEnum { variants, .. }) = reg.get(id) else { return unknown() };
+                let ev = match j {
+                    Json::String(name) => match variants.iter().find(|v| v.name == *name) {
+                        Some(variant) => variant.id,
+                        None => return err(JsonErrorKind::Variant(name.clone())),
+                    },
+                    j => u64_from_json(j)?,
+                };
+                Value::CEnum(*id, ev)
+            }
+            Type::Enum(id) => {
+                let Some(Def::Enum { variants, .. }) = reg.get(id) else { return unknown() };
+                let (name, payload) = match j {
+                    Json::String(name) => (name, None),
+                    Json::Object(map) if map.len() == 1 => {
+                        let (name, payload) = map.iter().next().unwrap();
+                        (name, Some(payload))
+                    }
+                    _ => return expected("variant name or object with a single key"),
+                };
+                let Some(variant) = variants.iter().find(|v| v.name == *name || v.id.to_string() == *name) else {
+                    return err(JsonErrorKind::Variant(name.clone()));
+                };
+                let v = match payload {
+                    Some(j) => Value::from_json_with(j, &variant.ty, reg),
+                    None => Value::from_json_with(&Json::Null, &variant.ty, reg),
+                };
+                Value::Enum(*id, variant.id, Box::new(v.map_err(|e| e.at(PathSeg::Variant(*id, variant.id)))?))
+            }
+            Type::Struct(id) => {
+                let Some(fields) = reg.fields(id) else { return unknown() };
+                let s = match j {
+                    Json::Object(map) => {
+                        if let Some(k) = map.keys().find(|k| !fields.iter().any(|f| f.name == **k)) {
+                            return err(JsonErrorKind::Field(k.clone()));
+                        }
+                        fields.iter().enumerate().map(|(i, f)| match map.get(&f.name) {
+                            Some(j) => Value::from_json_with(j, &f.ty, reg).map_err(|e| e.at(PathSeg::Index(SeqKind::Struct(*id), i))),
+                            // only optional fields may be left out
+                            None => match &f.ty {
+                                Type::Option(t) => Ok(Value::Option((**t).clone(), Box::new(None))),
+                                _ => err(JsonErrorKind::MissingField(f.name.clone())),
+                            },
+                        }).collect::<JsonResult<_>>()?
+                    }
+                    j => {
+                        let a = array_from_json(j)?;
+                        if a.len() != fields.len() {
+                            return err(JsonErrorKind::Len { expected: fields.len(), found: a.len() });
+                        }
+                        seq_from_json(SeqKind::Struct(*id), a.iter(), |i, j| Value::from_json_with(j, &fields[i].ty, reg))?
+                    }
+                };
+                Value::Struct(*id, s)
+            }
+            Type::Type | Type::TypeId => {
+                let v = Value::from_json(j)?;
+                if v.as_type() != *t {
+                    return expected("tagged type or type id");
+                }
+                v
+            }
+        })
+    }
+}
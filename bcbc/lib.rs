@@ -4,7 +4,9 @@
 #![no_std]
 
 extern crate alloc;
-use alloc::boxed::Box;
+#[cfg(feature = "std")]
+extern crate std;
+use alloc::{boxed::Box, vec, vec::Vec};
 
 use foundations::{error_enum, num_enum};
 pub use byte_storage;
@@ -16,6 +18,12 @@ pub type TupleItemId = u8;
 mod typeid;
 pub use typeid::*;
 
+mod path;
+pub use path::*;
+
+mod registry;
+pub use registry::*;
+
 num_enum! {
     pub enum Tag {
         Unknown = 0x00,
@@ -44,6 +52,9 @@ num_enum! {
         Struct  = 0x17,
         Type    = 0x18,
         TypeId  = 0x19,
+        Char    = 0x1a,
+        U128    = 0x1b,
+        I128    = 0x1c,
     } as u8 else Error::Tag
 }
 
@@ -102,7 +113,17 @@ num_enum! {
     } as u8 else Fatal::Ext1
 }
 
+// 0x3..=0x7 are reserved
+num_enum! {
+    pub enum Ext2 {
+        Char = 0x0,
+        U128 = 0x1,
+        I128 = 0x2,
+    } as u8 else Error::Ext2
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Type {
     Unknown,
 
@@ -121,6 +142,10 @@ pub enum Type {
     F32,
     F64,
 
+    Char,
+    U128,
+    I128,
+
     String,
     Bytes,
 
@@ -141,6 +166,11 @@ pub enum Type {
 // TODO variant structs?
 // TODO no value & matching r&w api
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(
+    serialize = "B: AsRef<[u8]> + ByteStorage",
+    deserialize = "B: Storage, ByteStr<B>: serde::Deserialize<'de>",
+)))]
 pub enum Value<B> {
     Unit,
     Bool(bool),
@@ -157,8 +187,12 @@ pub enum Value<B> {
     F32(u32),
     F64(u64),
 
+    Char(char),
+    U128(u128),
+    I128(i128),
+
     String(ByteStr<B>),
-    Bytes(B),
+    Bytes(#[cfg_attr(feature = "serde", serde(with = "serde_impl::bytes"))] B),
 
     Option(Type, Box<Option<Value<B>>>),
     List(Type, Box<[Value<B>]>),
@@ -175,43 +209,227 @@ pub enum Value<B> {
     TypeId(TypeId),
 }
 
+// every *Start is followed by its inner values (two per entry for maps) and then a matching End
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Event<B> {
+    // values without inner values, including `None`s and `CEnum`s
+    Scalar(Value<B>),
+
+    SomeStart { ty: Type },
+    ListStart { len: usize, ty: Type },
+    MapStart { len: usize, tk: Type, tv: Type },
+
+    TupleStart { len: usize },
+
+    AliasStart { id: TypeId },
+    EnumStart { id: TypeId, ev: EnumVariantId },
+    StructStart { id: TypeId, len: usize },
+
+    End,
+}
+
 pub const EXT8:  L4 = L4::F32;  // 0xc
 pub const EXT16: L4 = L4::F64;  // 0xd
 pub const EXT32: L4 = L4::EXT1; // 0xe
 pub const EXT64: L4 = L4::EXT2; // 0xf
 
-pub trait Schema {
+// Generic over the storage so that implementations can bound it, e.g. with `AsRef<[u8]> + ByteStorage`
+// to read strings out of the value.
+pub trait Schema<B>: Sized {
+    // `Anonymous` for types without a definition
     const ID: TypeId;
-    fn serialize<B>(self) -> Value<B>;
-    fn deserialize<B>(val: Value<B>) -> Self;
+    fn ty() -> Type;
+    fn serialize(self) -> Value<B>;
+    fn deserialize(val: Value<B>) -> SchemaResult<Self>;
+
+    // overridden by `u8` so that `Vec<u8>` and `[u8; N]` are `Bytes` instead of lists in owned storages
+    #[doc(hidden)]
+    fn __vec_ty() -> Type {
+        Type::List(Box::new(Self::ty()))
+    }
+
+    #[doc(hidden)]
+    fn __serialize_vec(v: Vec<Self>) -> Value<B> {
+        Value::List(Self::ty(), v.into_iter().map(Self::serialize).collect())
+    }
+
+    #[doc(hidden)]
+    fn __deserialize_vec(val: Value<B>) -> SchemaResult<Vec<Self>> {
+        val.into_list()?
+            .into_vec()
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| Self::deserialize(v).map_err(|e| e.at(PathSeg::Index(SeqKind::List, i))))
+            .collect()
+    }
+}
+
+// shared by reader and writer, so that everything encoded with some options can be decoded with the same
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Options {
+    // of a single string, bytes or sequence
+    pub max_len: u64,
+    // sum of sequence lengths in a value, counted when allocating them
+    pub max_alloc: usize,
+    // of nested values and types
+    pub max_depth: usize,
+    // in bytes, of the whole input when decoding
+    pub max_input: usize,
+    // in bytes, of the whole output when encoding, should not exceed `max_input` for the output to be decodable with
+    // the same options
+    pub max_output: usize,
+    // run `Value::typecheck` on whole values before encoding or after decoding, and check each inner value of streams
+    // against the types declared by the containers it is in
+    pub typecheck: bool,
 }
 
-// TODO temp solution
-pub const SIZE_MAX: usize = u16::MAX as usize;
+impl Options {
+    // safe for untrusted input
+    pub const DEFAULT: Options = Options {
+        max_len: u16::MAX as u64,
+        max_alloc: 1 << 20,
+        max_depth: 128,
+        max_input: 1 << 26,
+        max_output: 1 << 26,
+        typecheck: false,
+    };
+
+    // for trusted input only, lifting every limit except `max_depth`, which stays at 1024 as dropping, comparing and
+    // formatting values and types recurse and would overflow a default thread stack on deeper ones
+    pub const TRUSTED: Options = Options {
+        max_len: u64::MAX,
+        max_alloc: usize::MAX,
+        max_depth: 1024,
+        max_input: usize::MAX,
+        max_output: usize::MAX,
+        typecheck: false,
+    };
+
+    #[inline]
+    pub const fn len_allowed(&self, len: u64) -> bool {
+        len <= self.max_len
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options::DEFAULT
+    }
+}
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct FullError<B> {
     pub err: Error,
     pub buf: B,
     pub pos: usize,
+    // of the value whose header, type annotation or payload failed, or of the type error
+    pub path: Path,
+}
+
+// of `Value::decode_lenient`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub err: Error,
+    // like `FullError`
+    pub pos: usize,
+    pub path: Path,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    // a non-canonical encoding that was accepted
+    Warning,
+    // the list item the value is in was dropped
+    Skipped,
+    // decoding stopped, the value is partial
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recovered<B> {
+    // None if not even a partial value was read
+    pub value: Option<Value<B>>,
+    pub diags: Vec<Diagnostic>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeError {
+    pub path: Path,
+    pub kind: TypeErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TypeErrorKind {
+    Mismatch { expected: Type, found: Type },
+    // of a tuple in a stream, whose item types are not known when it starts
+    TupleMismatch { expected: Type, len: usize },
+    // the rest only with a registry
+    UnknownVariant { id: TypeId, ev: EnumVariantId },
+    FieldCount { id: TypeId, expected: usize, found: usize },
+}
+
+// of the text notation of `Type`, `TypeId`, `StdId` and `HashId`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseTypeError {
+    // in bytes
+    pub pos: usize,
+    pub expected: &'static str,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchemaError {
+    // of the inner value the error occurred at
+    pub path: Path,
+    pub kind: SchemaErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SchemaErrorKind {
+    Tag { expected: Tag, found: Tag },
+    TypeId { expected: TypeId, found: TypeId },
+    Variant { id: TypeId, ev: EnumVariantId },
+    Len { expected: usize, found: usize },
 }
 
 error_enum! {
     #[derive(Clone, Debug, PartialEq, Eq)]
     pub enum Error {
-        // TODO temp solution
-        TooLongLen(usize),
+        TooLongLen(u64),
+        TooManyItems(usize),
+        TooDeep(usize),
+        TooLongInput(usize),
         Tag(u8),
         BytevarIntSign { buf: [u8; 8] },
         BytevarLongerThanType { len: usize, nlen: usize, buf: [u8; 8] },
         BytevarLongerThanExpected { len: usize, nlen: usize, exp_len: usize, buf: [u8; 8] },
         BytevarNegZero { buf: [u8; 8] },
         ExtvarTooLong { l4: L4, exp_l4: L4, u: u64 },
-        Ext2NotImplemented,
+        Ext2(u8),
+        Char(u32),
     } convert {
         // Utf8 => { pos: usize, len: usize, error: core::str::Utf8Error },
         Utf8 => core::str::Utf8Error,
         Read => ReadError,
+        Type => TypeError,
+        Fatal => Fatal,
+    }
+}
+
+error_enum! {
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum EncodeError {
+        TooLongLen(u64),
+        TooManyItems(usize),
+        TooDeep(usize),
+        TooLongOutput(usize),
+        TooLongTuple(usize),
+        LenMismatch { len: usize, written: usize },
+        UnexpectedEnd,
+        Unfinished { depth: usize },
+    } convert {
+        Write => WriteError,
+        Type => TypeError,
         Fatal => Fatal,
     }
 }
@@ -224,20 +442,119 @@ pub enum Fatal {
     H4ToN(H4),
     NToH4(usize),
     H4ToExt1(H4),
+    H4ToExt2(H4),
     ToSize(u64),
     FromSize(usize),
     // TODO debug vars
     BytevarSlicing,
+    UnexpectedEnd,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SerdeError {
+    // of the inner value the error occurred at, empty when encoding or decoding
+    pub path: Path,
+    pub kind: SerdeErrorKind,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SerdeErrorKind {
+    // from serde impls
+    Custom(alloc::string::String),
+    Schema(SchemaErrorKind),
+    // values that have no serde equivalent
+    Unsupported(Tag),
+    Utf8(core::str::Utf8Error),
+    Decode { err: Error, pos: usize },
+    Encode(EncodeError),
+}
+
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, Debug)]
+pub struct SerdeOptions {
+    pub opts: Options,
+    // of structs and enums, by the names serde passes
+    pub ids: fn(&'static str) -> TypeId,
+}
+
+#[cfg(feature = "json")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JsonError {
+    // of the inner value the error occurred at
+    pub path: Path,
+    pub kind: JsonErrorKind,
+}
+
+#[cfg(feature = "json")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JsonErrorKind {
+    // what JSON value was expected
+    Expected(&'static str),
+    OutOfRange,
+    Hex,
+    Len { expected: usize, found: usize },
+    Tag(alloc::string::String),
+    // `Unknown` or not in the registry
+    Type(Type),
+    Variant(alloc::string::String),
+    Field(alloc::string::String),
+    MissingField(alloc::string::String),
+    // of types and ids
+    Serde(alloc::string::String),
 }
 
 // TODO: use uniform Error like serde_json?
 type Result<T> = core::result::Result<T, Error>;
 type FullResult<T, B> = core::result::Result<T, FullError<B>>;
 type FatalResult<T> = core::result::Result<T, Fatal>;
+type EncodeResult<T> = core::result::Result<T, EncodeError>;
+type CheckResult<T> = core::result::Result<T, TypeError>;
+type ParseTypeResult<T> = core::result::Result<T, ParseTypeError>;
+// public since implementations of `Schema` return it
+pub type SchemaResult<T> = core::result::Result<T, SchemaError>;
+#[cfg(feature = "serde")]
+pub type SerdeResult<T> = core::result::Result<T, SerdeError>;
+#[cfg(feature = "json")]
+pub type JsonResult<T> = core::result::Result<T, JsonError>;
 
 pub mod casting;
+mod check;
+mod text;
+mod explain;
+pub use explain::*;
+mod error;
+mod schema;
+pub use schema::*;
 pub mod reader;
 pub mod writer;
 
+#[cfg(feature = "serde")]
+mod ser;
+#[cfg(feature = "serde")]
+pub use ser::*;
+#[cfg(feature = "serde")]
+mod de;
+#[cfg(feature = "serde")]
+pub use de::*;
+#[cfg(feature = "serde")]
+mod serde_impl;
+#[cfg(feature = "json")]
+mod json;
+
+#[cfg(feature = "derive")]
+pub use bcbc_derive::Schema;
+
+// used by the derive macro
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::{boxed::Box, vec};
+}
+
+// so that derived impls work in tests
+#[cfg(test)]
+extern crate self as bcbc;
+
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,92 @@
+use core::fmt;
+use super::*;
+
+// the kind of a container with indexed inner values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SeqKind {
+    List,
+    Tuple,
+    Struct(TypeId),
+}
+
+// one step from a container value into one of its inner values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PathSeg {
+    // payload of `Some`
+    Some,
+    // item of a list or tuple, or field of a struct
+    Index(SeqKind, usize),
+    // key and value of a map entry
+    Key(usize),
+    Value(usize),
+    // inner value of an alias
+    Alias(TypeId),
+    // payload of an enum
+    Variant(TypeId, EnumVariantId),
+    // type annotation of the value, or the payload of a `Type` value; not further into the type
+    Type,
+}
+
+impl PathSeg {
+    // of the `i`th inner value of the container whose first inner value this is, keys and values both count for a map
+    pub(crate) fn nth(self, i: usize) -> PathSeg {
+        match self {
+            PathSeg::Index(kind, _) => PathSeg::Index(kind, i),
+            PathSeg::Key(_) => match i % 2 {
+                0 => PathSeg::Key(i / 2),
+                _ => PathSeg::Value(i / 2),
+            },
+            seg => seg,
+        }
+    }
+}
+
+// from the top-level value, empty for the top-level value itself
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Path(pub Vec<PathSeg>);
+
+impl Path {
+    pub const fn new() -> Path {
+        Path(Vec::new())
+    }
+
+    pub fn segs(&self) -> &[PathSeg] {
+        &self.0
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<PathSeg>> for Path {
+    fn from(segs: Vec<PathSeg>) -> Path {
+        Path(segs)
+    }
+}
+
+// like `tuple[3] → map[12].value → list[0] → enum(std:01:5f49)[5].payload`, or `root`
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_root() {
+            return f.write_str("root");
+        }
+        for (i, seg) in self.0.iter().enumerate() {
+            if i != 0 {
+                f.write_str(" → ")?;
+            }
+            match seg {
+                PathSeg::Some => f.write_str("some")?,
+                PathSeg::Index(SeqKind::List, i) => write!(f, "list[{}]", i)?,
+                PathSeg::Index(SeqKind::Tuple, i) => write!(f, "tuple[{}]", i)?,
+                PathSeg::Index(SeqKind::Struct(id), i) => write!(f, "struct({})[{}]", id, i)?,
+                PathSeg::Key(i) => write!(f, "map[{}].key", i)?,
+                PathSeg::Value(i) => write!(f, "map[{}].value", i)?,
+                PathSeg::Alias(id) => write!(f, "alias({})", id)?,
+                PathSeg::Variant(id, ev) => write!(f, "enum({})[{}].payload", id, ev)?,
+                PathSeg::Type => f.write_str("type")?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,22 +1,39 @@
 use foundations::byterepr::*;
 use super::*;
 
-// We can't avoid allocs completely because of nested values and indefinite-length sequences.
-// So we should check for allocation at sequence creates to ensure no panic.
-#[inline(always)]
-fn alloc_seq<T, F: FnMut(()) -> Result<T>>(size: usize, f: F) -> Result<Box<[T]>> {
-    core::iter::repeat(()).take(size).map(f).collect()
-}
+use core::ops::ControlFlow;
+use alloc::string::{String, ToString};
 
 struct Reader<I> {
     inner: byte_storage::Reader<I>,
+    opts: Options,
+    depth: usize,
+    // sum of sizes passed to alloc
+    items: usize,
+    // only when explaining or collecting spans
+    notes: Option<Vec<Note>>,
+    // payload notes in the text notation, only when explaining
+    text: bool,
+    // of the value being read when failed
+    path: Path,
+    // whether a type is being read, so a failure is in the annotation of the value at `path`
+    in_ty: bool,
+    // only when lenient
+    diags: Option<Vec<Diagnostic>>,
+    // index of the list in the value stack whose item being read is dropped, only when lenient
+    skip: Option<usize>,
 }
 
 // wrapper impls
 impl<B: AsRef<[u8]> + ByteStorage, I: Input<Storage = B>> Reader<I> {
     #[inline(always)]
-    pub fn new(bytes: B) -> Self {
-        Self { inner: byte_storage::Reader::new(bytes) }
+    pub fn new(bytes: B, opts: Options) -> Self {
+        Self { inner: byte_storage::Reader::new(bytes), opts, depth: 0, items: 0, notes: None, text: false, path: Path::new(), in_ty: false, diags: None, skip: None }
+    }
+
+    #[inline(always)]
+    pub fn pos(&self) -> usize {
+        self.inner.pos()
     }
 
     #[inline(always)]
@@ -64,7 +81,7 @@ macro_rules! num_impl {
 }
 
 impl<B: AsRef<[u8]> + ByteStorage, I: Input<Storage = B>> Reader<I> {
-    fn finish_with<T>(self, res: Result<T>) -> FullResult<T, B> {
+    fn finish_with<T>(mut self, res: Result<T>) -> FullResult<T, B> {
         match res {
             Ok(val) => {
                 match self.finish() {
@@ -72,16 +89,109 @@ impl<B: AsRef<[u8]> + ByteStorage, I: Input<Storage = B>> Reader<I> {
                     Err((err, reader)) => {
                         let (input, pos) = reader.into_parts();
                         let buf = input.leak();
-                        Err(FullError { err: err.into(), buf, pos })
+                        Err(FullError { err: err.into(), buf, pos, path: Path::new() })
                     }
                 }
             },
             Err(err) => {
+                let path = match &err {
+                    Error::Type(e) => e.path.clone(),
+                    _ => core::mem::take(&mut self.path),
+                };
                 let (input, pos) = self.into_parts();
                 let buf = input.leak();
-                Err(FullError { err, buf, pos })
+                Err(FullError { err, buf, pos, path })
+            }
+        }
+    }
+
+    fn check_input(&self) -> Result<()> {
+        let len = self.inner.rest_len();
+        if len > self.opts.max_input {
+            return Err(Error::TooLongInput(len));
+        }
+        Ok(())
+    }
+
+    // covers the bytes read since the last note, skipped if none (except errors)
+    fn note(&mut self, kind: impl FnOnce() -> NoteKind) {
+        let pos = self.pos();
+        let depth = self.depth;
+        let Some(notes) = self.notes.as_mut() else {
+            return;
+        };
+        let start = notes.last().map_or(0, |n| n.range.end);
+        let kind = kind();
+        let depth = match kind {
+            NoteKind::Header(..) | NoteKind::Error(_) => depth,
+            _ if start == pos => return,
+            _ => depth + 1,
+        };
+        notes.push(Note { range: start..pos, depth, kind });
+    }
+
+    fn diag(&mut self, severity: Severity, err: Error, path: Path) {
+        let pos = self.pos();
+        if let Some(diags) = self.diags.as_mut() {
+            diags.push(Diagnostic { severity, err, pos, path });
+        }
+    }
+
+    // accepts a non-canonical but unambiguous encoding when lenient, the path is filled by val_with
+    fn warn(&mut self, err: Error) -> Result<()> {
+        if self.diags.is_none() {
+            return Err(err);
+        }
+        self.diag(Severity::Warning, err, Path::new());
+        Ok(())
+    }
+
+    // skips the list item the failed value is in when lenient, if the failed value is read through
+    fn recover(&mut self, err: Error, stack: &[PartialValue<B>]) -> Result<Event<B>> {
+        let read_through = matches!(
+            err,
+            Error::Utf8(_) | Error::Char(_) | Error::BytevarIntSign { .. } | Error::BytevarNegZero { .. } | Error::BytevarLongerThanType { .. }
+        );
+        let list = stack.iter().rposition(|p| matches!(p, PartialValue::List(..)));
+        match list {
+            Some(list) if read_through && self.diags.is_some() => {
+                self.diag(Severity::Skipped, err, Path::new());
+                // an outer one drops this anyway
+                let _ = self.skip.get_or_insert(list);
+                // never used
+                Ok(Event::Scalar(Value::Unit))
             }
+            _ => Err(err),
+        }
+    }
+
+    fn enter(&mut self) -> Result<()> {
+        if self.depth >= self.opts.max_depth {
+            return Err(Error::TooDeep(self.depth + 1));
         }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+
+    fn alloc(&mut self, size: usize) -> Result<()> {
+        let items = self.items.saturating_add(size);
+        if items > self.opts.max_alloc {
+            return Err(Error::TooManyItems(items));
+        }
+        self.items = items;
+        Ok(())
+    }
+
+    // We can't avoid allocs completely because of nested values and indefinite-length sequences.
+    // So we should check for allocation at sequence creates to ensure no panic.
+    // Every item takes at least one byte, so the capacity is also bounded by the rest of input.
+    fn alloc_seq<T>(&mut self, size: usize) -> Result<Vec<T>> {
+        self.alloc(size)?;
+        Ok(Vec::with_capacity(size.min(self.inner.rest_len())))
     }
 
     #[inline(always)]
@@ -95,7 +205,7 @@ impl<B: AsRef<[u8]> + ByteStorage, I: Input<Storage = B>> Reader<I> {
 
     fn typeid(&mut self) -> Result<TypeId> {
         let h8 = self.u8()?;
-        Ok(match h8 {
+        let id = match h8 {
             SCHEMA_HASH => {
                 let hash = self.bytes_sized()?;
                 TypeId::Hash(HashId { hash })
@@ -105,11 +215,42 @@ impl<B: AsRef<[u8]> + ByteStorage, I: Input<Storage = B>> Reader<I> {
                 let id = self.u16()?;
                 TypeId::Std(StdId { schema, id })
             }
-        })
+        };
+        self.note(|| NoteKind::TypeId(id));
+        Ok(id)
     }
 
+    // iterative, so that nesting is only bounded by opts.max_depth instead of the call stack
     fn ty(&mut self) -> Result<Type> {
+        let mut stack: Vec<PartialType> = Vec::new();
+        self.in_ty = true;
+        loop {
+            let Some(mut t) = self.ty_head(&mut stack)? else {
+                continue;
+            };
+            loop {
+                let Some(partial) = stack.pop() else {
+                    self.in_ty = false;
+                    return Ok(t);
+                };
+                match partial.add(t) {
+                    ControlFlow::Break(full) => {
+                        self.leave();
+                        t = full;
+                    }
+                    ControlFlow::Continue(partial) => {
+                        stack.push(partial);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    // reads a direct type, or the start of a container type and pushes it to the stack and returns None
+    fn ty_head(&mut self, stack: &mut Vec<PartialType>) -> Result<Option<Type>> {
         let tag = self.u8()?.try_into()?;
+        self.note(|| NoteKind::Tag(tag));
         macro_rules! ty_impl {
             (
                 direct {$($direct_name:ident)*}
@@ -117,11 +258,11 @@ impl<B: AsRef<[u8]> + ByteStorage, I: Input<Storage = B>> Reader<I> {
                 $($tt:tt)*
             ) => {
                 match tag {
-                    $(Tag::$direct_name => Type::$direct_name,)*
+                    $(Tag::$direct_name => Some(Type::$direct_name),)*
                     $($tt)*
                     $(Tag::$typeid_name => {
                         let r = self.typeid()?;
-                        Type::$typeid_name(r)
+                        Some(Type::$typeid_name(r))
                     })*
                 }
             };
@@ -143,6 +284,9 @@ impl<B: AsRef<[u8]> + ByteStorage, I: Input<Storage = B>> Reader<I> {
                 F16
                 F32
                 F64
+                Char
+                U128
+                I128
                 String
                 Bytes
                 Type
@@ -155,22 +299,33 @@ impl<B: AsRef<[u8]> + ByteStorage, I: Input<Storage = B>> Reader<I> {
                 Struct
             }
             Tag::Option => {
-                let t = self.ty()?;
-                Type::Option(Box::new(t))
+                self.enter()?;
+                stack.push(PartialType::Option);
+                None
             },
             Tag::List => {
-                let t = self.ty()?;
-                Type::List(Box::new(t))
+                self.enter()?;
+                stack.push(PartialType::List);
+                None
             },
             Tag::Map => {
-                let tk = self.ty()?;
-                let tv = self.ty()?;
-                Type::Map(Box::new(tk), Box::new(tv))
+                self.enter()?;
+                stack.push(PartialType::Map(None));
+                None
             },
             Tag::Tuple => {
-                let size = self.u8()? as usize;
-                let s = alloc_seq(size, |_| self.ty())?;
-                Type::Tuple(s)
+                let size = self.u8()?;
+                self.note(|| NoteKind::TupleSize(size));
+                let size = size as usize;
+                self.enter()?;
+                let s = self.alloc_seq(size)?;
+                if size == 0 {
+                    self.leave();
+                    Some(Type::Tuple(s.into_boxed_slice()))
+                } else {
+                    stack.push(PartialType::Tuple(size, s));
+                    None
+                }
             },
         })
     }
@@ -195,83 +350,155 @@ impl<B: AsRef<[u8]> + ByteStorage, I: Input<Storage = B>> Reader<I> {
             EXT64
         };
         if exp_l4 != l4 {
-            Err(Error::ExtvarTooLong { l4, exp_l4, u })
-        } else {
-            Ok(u)
+            self.warn(Error::ExtvarTooLong { l4, exp_l4, u })?;
         }
+        self.note(|| NoteKind::Ext(u));
+        Ok(u)
     }
 
     fn extszvar(&mut self, l4: L4) -> Result<usize> {
         let sz = self.extvar(l4)?;
-        let sz = sz.try_into().map_err(|_| Fatal::ToSize(sz))?;
-        if sz <= SIZE_MAX {
-            Ok(sz)
-        } else {
-            Err(Error::TooLongLen(sz))
+        if !self.opts.len_allowed(sz) {
+            return Err(Error::TooLongLen(sz));
         }
+        Ok(sz.try_into().map_err(|_| Fatal::ToSize(sz))?)
     }
 
-    // TODO Iterator
-    fn val_seq(&mut self, size: usize) -> Result<Box<[Value<B>]>> {
-        alloc_seq(size, |_| self.val())
+    fn val(&mut self) -> Result<Value<B>> {
+        let mut stack = Vec::new();
+        let res = self.val_with(&mut stack);
+        if res.is_err() {
+            self.path = stack.iter().map(PartialValue::seg).chain(self.in_ty.then_some(PathSeg::Type)).collect::<Vec<_>>().into();
+        }
+        res
     }
 
-    #[allow(clippy::type_complexity)]
-    fn val_seq_map(&mut self, size: usize) -> Result<Box<[(Value<B>, Value<B>)]>> {
-        alloc_seq(size, |_| {
-            let k = self.val()?;
-            let v = self.val()?;
-            Ok((k, v))
-        })
+    // iterative like ty
+    fn val_with(&mut self, stack: &mut Vec<PartialValue<B>>) -> Result<Value<B>> {
+        loop {
+            let diags = self.diags.as_ref().map_or(0, Vec::len);
+            let ev = match self.event() {
+                Ok(ev) => ev,
+                Err(err) => self.recover(err, stack)?,
+            };
+            if let Some(new) = self.diags.as_mut().and_then(|d| d.get_mut(diags..)).filter(|d| !d.is_empty()) {
+                let path = Path::from(stack.iter().map(PartialValue::seg).collect::<Vec<_>>());
+                for diag in new {
+                    diag.path = path.clone();
+                }
+            }
+            let mut step = match ev {
+                Event::Scalar(v) => ControlFlow::Break(v),
+                ev => {
+                    self.enter()?;
+                    let step = self.start(ev)?;
+                    if let ControlFlow::Break(_) = step {
+                        self.leave();
+                    }
+                    step
+                }
+            };
+            loop {
+                match step {
+                    ControlFlow::Continue(partial) => {
+                        stack.push(partial);
+                        break;
+                    }
+                    ControlFlow::Break(v) => {
+                        let Some(partial) = stack.pop() else {
+                            return Ok(v);
+                        };
+                        step = if self.skip == Some(stack.len()) {
+                            self.skip = None;
+                            partial.skip()
+                        } else {
+                            partial.add(v)
+                        };
+                        if let ControlFlow::Break(_) = step {
+                            self.leave();
+                        }
+                    }
+                }
+            }
+        }
     }
 
-    fn val(&mut self) -> Result<Value<B>> {
+    fn checked_val(&mut self) -> Result<Value<B>> {
+        let val = self.val()?;
+        if self.opts.typecheck {
+            val.typecheck()?;
+        }
+        Ok(val)
+    }
+
+    fn start(&mut self, ev: Event<B>) -> Result<ControlFlow<Value<B>, PartialValue<B>>> {
+        let partial = match ev {
+            Event::SomeStart { ty } => PartialValue::Some(ty),
+            Event::ListStart { len, ty } => PartialValue::List(ty, len, self.alloc_seq(len)?),
+            Event::MapStart { len, tk, tv } => PartialValue::Map((tk, tv), len, self.alloc_seq(len)?, None),
+            Event::TupleStart { len } => PartialValue::Tuple(len, self.alloc_seq(len)?),
+            Event::AliasStart { id } => PartialValue::Alias(id),
+            Event::EnumStart { id, ev } => PartialValue::Enum(id, ev),
+            Event::StructStart { id, len } => PartialValue::Struct(id, len, self.alloc_seq(len)?),
+            Event::Scalar(_) | Event::End => return Err(Fatal::UnexpectedEnd.into()),
+        };
+        Ok(partial.check())
+    }
+
+    // reads a header and everything before its inner values
+    fn event(&mut self) -> Result<Event<B>> {
         let (h4, l4) = casting::to_h4l4(self.u8()?)?;
+        self.note(|| NoteKind::Header(h4, l4));
+        let ev = self.event_body(h4, l4)?;
+        // what is left after the header, extvar, types and TypeId
+        if let Event::Scalar(v) = &ev {
+            let text = self.text;
+            self.note(|| NoteKind::Payload(if text { v.to_string() } else { String::new() }));
+        }
+        Ok(ev)
+    }
+
+    fn event_body(&mut self, h4: H4, l4: L4) -> Result<Event<B>> {
         Ok(match h4 {
             H4::String => {
                 let len = self.extszvar(l4)?;
                 let b = self.bytes(len)?;
-                Value::String(ByteStr::from_utf8(b)?)
+                Event::Scalar(Value::String(ByteStr::from_utf8(b)?))
             }
             H4::Bytes => {
                 let len = self.extszvar(l4)?;
                 let b = self.bytes(len)?;
-                Value::Bytes(b)
+                Event::Scalar(Value::Bytes(b))
             }
             H4::List => {
                 let len = self.extszvar(l4)?;
-                let t = self.ty()?;
-                let s = self.val_seq(len)?;
-                Value::List(t, s)
+                let ty = self.ty()?;
+                Event::ListStart { len, ty }
             }
             H4::Map => {
                 let len = self.extszvar(l4)?;
                 let tk = self.ty()?;
                 let tv = self.ty()?;
-                let s = self.val_seq_map(len)?;
-                Value::Map((tk, tv), s)
+                Event::MapStart { len, tk, tv }
             }
             H4::Tuple => {
                 let len = self.extszvar(l4)?;
-                let s = self.val_seq(len)?;
-                Value::Tuple(s)
+                Event::TupleStart { len }
             }
             H4::CEnum => {
                 let ev = self.extvar(l4)?;
                 let r = self.typeid()?;
-                Value::CEnum(r, ev)
+                Event::Scalar(Value::CEnum(r, ev))
             }
             H4::Enum => {
                 let ev = self.extvar(l4)?;
-                let r = self.typeid()?;
-                let v = self.val()?;
-                Value::Enum(r, ev, Box::new(v))
+                let id = self.typeid()?;
+                Event::EnumStart { id, ev }
             }
             H4::Struct => {
                 let len = self.extszvar(l4)?;
-                let r = self.typeid()?;
-                let s = self.val_seq(len)?;
-                Value::Struct(r, s)
+                let id = self.typeid()?;
+                Event::StructStart { id, len }
             }
             h4 => {
                 macro_rules! bytevar_impl {
@@ -280,12 +507,16 @@ impl<B: AsRef<[u8]> + ByteStorage, I: Input<Storage = B>> Reader<I> {
                         let mut buf = [0; 8];
                         self.read_exact(&mut buf[$rangefn(len)])?;
                         const NLEN: usize = core::mem::size_of::<$nty>();
-                        if len > NLEN {
-                            return Err(Error::BytevarLongerThanType { len, nlen: NLEN, buf });
-                        }
                         let exp_len = $lenfn(&buf);
-                        if len != exp_len {
-                            return Err(Error::BytevarLongerThanExpected { len, nlen: NLEN, exp_len, buf });
+                        if len > NLEN {
+                            let err = Error::BytevarLongerThanType { len, nlen: NLEN, buf };
+                            // only zeros beyond the type are accepted
+                            if exp_len > NLEN {
+                                return Err(err);
+                            }
+                            self.warn(err)?;
+                        } else if len != exp_len {
+                            self.warn(Error::BytevarLongerThanExpected { len, nlen: NLEN, exp_len, buf })?;
                         }
                         let ubuf = buf[$rangefn(NLEN)].try_into().map_err(|_| Fatal::BytevarSlicing)?;
                         let u = <$nty>::from_bytes(ubuf);
@@ -340,7 +571,7 @@ impl<B: AsRef<[u8]> + ByteStorage, I: Input<Storage = B>> Reader<I> {
                     };
                 }
 
-                numl4_impl! {
+                Event::Scalar(numl4_impl! {
                     U {
                         U8 u8
                         U16 u16
@@ -370,13 +601,12 @@ impl<B: AsRef<[u8]> + ByteStorage, I: Input<Storage = B>> Reader<I> {
                             Value::Option(t, Box::new(None))
                         }
                         Ext1::Some => {
-                            let t = self.ty()?;
-                            Value::Option(t, Box::new(Some(self.val()?)))
+                            let ty = self.ty()?;
+                            return Ok(Event::SomeStart { ty });
                         }
                         Ext1::Alias => {
-                            let r = self.typeid()?;
-                            let v = self.val()?;
-                            Value::Alias(r, Box::new(v))
+                            let id = self.typeid()?;
+                            return Ok(Event::AliasStart { id });
                         }
                         Ext1::Type => {
                             let t = self.ty()?;
@@ -387,24 +617,375 @@ impl<B: AsRef<[u8]> + ByteStorage, I: Input<Storage = B>> Reader<I> {
                             Value::TypeId(r)
                         }
                     }
-                    L4::EXT2 => return Err(Error::Ext2NotImplemented),
+                    // fixed-width payloads
+                    L4::EXT2 => match h4.to_ext2()? {
+                        Ext2::Char => {
+                            let u = self.u32()?;
+                            Value::Char(char::from_u32(u).ok_or(Error::Char(u))?)
+                        }
+                        Ext2::U128 => Value::U128(u128::from_be_bytes(self.bytes_sized()?)),
+                        Ext2::I128 => Value::I128(i128::from_be_bytes(self.bytes_sized()?)),
+                    }
+                })
+            }
+        })
+    }
+}
+
+// a container type whose inner types are being read
+enum PartialType {
+    Option,
+    List,
+    Map(Option<Type>),
+    Tuple(usize, Vec<Type>),
+}
+
+impl PartialType {
+    fn add(self, t: Type) -> ControlFlow<Type, PartialType> {
+        match self {
+            PartialType::Option => ControlFlow::Break(Type::Option(Box::new(t))),
+            PartialType::List => ControlFlow::Break(Type::List(Box::new(t))),
+            PartialType::Map(None) => ControlFlow::Continue(PartialType::Map(Some(t))),
+            PartialType::Map(Some(tk)) => ControlFlow::Break(Type::Map(Box::new(tk), Box::new(t))),
+            PartialType::Tuple(size, mut s) => {
+                s.push(t);
+                if s.len() == size {
+                    ControlFlow::Break(Type::Tuple(s.into_boxed_slice()))
+                } else {
+                    ControlFlow::Continue(PartialType::Tuple(size, s))
+                }
+            }
+        }
+    }
+}
+
+// a container value whose inner values are being read, with the declared lengths of sequences
+enum PartialValue<B> {
+    Some(Type),
+    List(Type, usize, Vec<Value<B>>),
+    Map((Type, Type), usize, Vec<(Value<B>, Value<B>)>, Option<Value<B>>),
+    Tuple(usize, Vec<Value<B>>),
+    Alias(TypeId),
+    Enum(TypeId, EnumVariantId),
+    Struct(TypeId, usize, Vec<Value<B>>),
+}
+
+impl<B> PartialValue<B> {
+    // of the inner value being read
+    fn seg(&self) -> PathSeg {
+        match self {
+            PartialValue::Some(_) => PathSeg::Some,
+            PartialValue::Alias(id) => PathSeg::Alias(*id),
+            PartialValue::Enum(id, ev) => PathSeg::Variant(*id, *ev),
+            PartialValue::List(_, _, s) => PathSeg::Index(SeqKind::List, s.len()),
+            PartialValue::Tuple(_, s) => PathSeg::Index(SeqKind::Tuple, s.len()),
+            PartialValue::Struct(id, _, s) => PathSeg::Index(SeqKind::Struct(*id), s.len()),
+            PartialValue::Map(_, _, s, None) => PathSeg::Key(s.len()),
+            PartialValue::Map(_, _, s, Some(_)) => PathSeg::Value(s.len()),
+        }
+    }
+
+    fn add(self, v: Value<B>) -> ControlFlow<Value<B>, PartialValue<B>> {
+        match self {
+            PartialValue::Some(t) => ControlFlow::Break(Value::Option(t, Box::new(Some(v)))),
+            PartialValue::Alias(r) => ControlFlow::Break(Value::Alias(r, Box::new(v))),
+            PartialValue::Enum(r, ev) => ControlFlow::Break(Value::Enum(r, ev, Box::new(v))),
+            PartialValue::List(t, len, mut s) => {
+                s.push(v);
+                PartialValue::List(t, len, s).check()
+            }
+            PartialValue::Map(tt, len, s, None) => ControlFlow::Continue(PartialValue::Map(tt, len, s, Some(v))),
+            PartialValue::Map(tt, len, mut s, Some(k)) => {
+                s.push((k, v));
+                PartialValue::Map(tt, len, s, None).check()
+            }
+            PartialValue::Tuple(len, mut s) => {
+                s.push(v);
+                PartialValue::Tuple(len, s).check()
+            }
+            PartialValue::Struct(r, len, mut s) => {
+                s.push(v);
+                PartialValue::Struct(r, len, s).check()
+            }
+        }
+    }
+
+    // drops the item being read of a list
+    fn skip(self) -> ControlFlow<Value<B>, PartialValue<B>> {
+        match self {
+            PartialValue::List(t, len, s) => PartialValue::List(t, len - 1, s).check(),
+            partial => ControlFlow::Continue(partial),
+        }
+    }
+
+    // with the inner values read so far, and the one being read if complete
+    fn close(self, v: Option<Value<B>>) -> Option<Value<B>> {
+        Some(match self {
+            PartialValue::Some(t) => Value::Option(t, Box::new(Some(v?))),
+            PartialValue::Alias(r) => Value::Alias(r, Box::new(v?)),
+            PartialValue::Enum(r, ev) => Value::Enum(r, ev, Box::new(v?)),
+            PartialValue::List(t, _, mut s) => {
+                s.extend(v);
+                Value::List(t, s.into_boxed_slice())
+            }
+            PartialValue::Map(tt, _, mut s, k) => {
+                if let (Some(k), Some(v)) = (k, v) {
+                    s.push((k, v));
                 }
+                Value::Map(tt, s.into_boxed_slice())
+            }
+            PartialValue::Tuple(_, mut s) => {
+                s.extend(v);
+                Value::Tuple(s.into_boxed_slice())
+            }
+            PartialValue::Struct(r, _, mut s) => {
+                s.extend(v);
+                Value::Struct(r, s.into_boxed_slice())
             }
         })
     }
+
+    // completes sequences that have all their declared items
+    fn check(self) -> ControlFlow<Value<B>, PartialValue<B>> {
+        match self {
+            PartialValue::List(t, len, s) if s.len() == len => ControlFlow::Break(Value::List(t, s.into_boxed_slice())),
+            PartialValue::Map(tt, len, s, None) if s.len() == len => ControlFlow::Break(Value::Map(tt, s.into_boxed_slice())),
+            PartialValue::Tuple(len, s) if s.len() == len => ControlFlow::Break(Value::Tuple(s.into_boxed_slice())),
+            PartialValue::Struct(r, len, s) if s.len() == len => ControlFlow::Break(Value::Struct(r, s.into_boxed_slice())),
+            partial => ControlFlow::Continue(partial),
+        }
+    }
 }
 
 impl<B: AsRef<[u8]> + ByteStorage> Value<B> {
     pub fn decode<I: Input<Storage = B>>(buf: B) -> FullResult<Value<B>, B> {
-        let mut reader = Reader::<I>::new(buf);
-        let val = reader.val();
+        Value::decode_with::<I>(buf, Options::DEFAULT)
+    }
+
+    pub fn decode_with<I: Input<Storage = B>>(buf: B, opts: Options) -> FullResult<Value<B>, B> {
+        let mut reader = Reader::<I>::new(buf, opts);
+        let val = reader.check_input().and_then(|()| reader.checked_val());
         reader.finish_with(val)
     }
 
     // cannot return FullResult
     pub fn decode_first_value<I: Input<Storage = B>>(buf: B) -> (Result<Value<B>>, B) {
-        let mut reader = Reader::<I>::new(buf);
-        let res = reader.val();
+        Value::decode_first_value_with::<I>(buf, Options::DEFAULT)
+    }
+
+    pub fn decode_first_value_with<I: Input<Storage = B>>(buf: B, opts: Options) -> (Result<Value<B>>, B) {
+        let mut reader = Reader::<I>::new(buf, opts);
+        let res = reader.check_input().and_then(|()| reader.checked_val());
         (res, reader.into_rest().leak())
     }
+
+    pub fn decode_lenient<I: Input<Storage = B>>(buf: B) -> Recovered<B> {
+        Value::decode_lenient_with::<I>(buf, Options::DEFAULT)
+    }
+
+    // accepts over-long bytevars and extvars, skips list items that fail but are read through, and returns what was
+    // read before a failure otherwise
+    pub fn decode_lenient_with<I: Input<Storage = B>>(buf: B, opts: Options) -> Recovered<B> {
+        let mut reader = Reader::<I>::new(buf, opts);
+        reader.diags = Some(Vec::new());
+        let mut stack = Vec::new();
+        let value = match reader.check_input().and_then(|()| reader.val_with(&mut stack)) {
+            Ok(val) => {
+                if opts.typecheck {
+                    if let Err(err) = val.typecheck() {
+                        let path = err.path.clone();
+                        reader.diag(Severity::Error, err.into(), path);
+                    }
+                }
+                let rest = reader.inner.rest_len();
+                if rest != 0 {
+                    reader.diag(Severity::Error, ReadError::TooLong { rest }.into(), Path::new());
+                }
+                Some(val)
+            }
+            Err(err) => {
+                let path = stack.iter().map(PartialValue::seg).chain(reader.in_ty.then_some(PathSeg::Type)).collect::<Vec<_>>().into();
+                reader.diag(Severity::Error, err, path);
+                let mut v = None;
+                while let Some(partial) = stack.pop() {
+                    if reader.skip == Some(stack.len()) {
+                        v = None;
+                    }
+                    v = partial.close(v);
+                }
+                v
+            }
+        };
+        Recovered { value, diags: reader.diags.unwrap_or_default() }
+    }
+
+    pub fn decode_spanned<I: Input<Storage = B>>(buf: B) -> FullResult<(Value<B>, Span), B> {
+        Value::decode_spanned_with::<I>(buf, Options::DEFAULT)
+    }
+
+    // also returns where each value is in the input
+    pub fn decode_spanned_with<I: Input<Storage = B>>(buf: B, opts: Options) -> FullResult<(Value<B>, Span), B> {
+        let mut reader = Reader::<I>::new(buf, opts);
+        reader.notes = Some(Vec::new());
+        let val = reader.check_input().and_then(|()| reader.checked_val());
+        let notes = reader.notes.take().unwrap_or_default();
+        reader.finish_with(val).map(|val| (val, Span::from_notes(&notes)))
+    }
+}
+
+impl<B: AsRef<[u8]> + ByteStorage + Clone> Value<B> {
+    pub fn explain<I: Input<Storage = B>>(buf: B) -> Explanation<B> {
+        Value::explain_with::<I>(buf, Options::DEFAULT)
+    }
+
+    // decodes like `decode_with`, noting every part of the encoding on the way
+    pub fn explain_with<I: Input<Storage = B>>(buf: B, opts: Options) -> Explanation<B> {
+        let mut reader = Reader::<I>::new(buf.clone(), opts);
+        reader.notes = Some(Vec::new());
+        reader.text = true;
+        let res = reader.check_input().and_then(|()| reader.checked_val()).and_then(|val| {
+            let rest = reader.inner.rest_len();
+            if rest != 0 {
+                let _ = reader.bytes(rest)?;
+                return Err(ReadError::TooLong { rest }.into());
+            }
+            Ok(val)
+        });
+        let value = match res {
+            Ok(val) => Some(val),
+            Err(err) => {
+                reader.note(|| NoteKind::Error(err));
+                None
+            }
+        };
+        Explanation { buf, notes: reader.notes.unwrap_or_default(), value }
+    }
+}
+
+// an open container of StreamReader, keys and values both count for a map
+struct Open {
+    // of the first inner value, the index counts up for sequences
+    first: PathSeg,
+    len: usize,
+    read: usize,
+    // inner values are checked against with `opts.typecheck`
+    ty: Type,
+}
+
+impl Open {
+    // of the last read inner value
+    fn seg(&self) -> PathSeg {
+        self.first.nth(self.read.saturating_sub(1))
+    }
+}
+
+pub struct StreamReader<I> {
+    // taken when finished or failed
+    reader: Option<Reader<I>>,
+    stack: Vec<Open>,
+    started: bool,
+}
+
+impl<B: AsRef<[u8]> + ByteStorage, I: Input<Storage = B>> StreamReader<I> {
+    pub fn new(buf: B) -> Self {
+        StreamReader::with_options(buf, Options::DEFAULT)
+    }
+
+    pub fn with_options(buf: B, opts: Options) -> Self {
+        StreamReader {
+            reader: Some(Reader::new(buf, opts)),
+            stack: Vec::new(),
+            started: false,
+        }
+    }
+
+    // 0 after finished or failed
+    pub fn pos(&self) -> usize {
+        self.reader.as_ref().map_or(0, Reader::pos)
+    }
+
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    // errors are the same as `Value::decode` (trailing bytes included), and returns `Ok(None)` after that
+    pub fn next_event(&mut self) -> FullResult<Option<Event<B>>, B> {
+        let Some(reader) = self.reader.as_mut() else {
+            return Ok(None);
+        };
+        let first = match self.stack.last_mut() {
+            Some(open) if open.read == open.len => {
+                let _ = self.stack.pop();
+                reader.leave();
+                return Ok(Some(Event::End));
+            }
+            Some(open) => {
+                open.read += 1;
+                false
+            }
+            None if self.started => {
+                return match self.reader.take() {
+                    Some(reader) => reader.finish_with(Ok(None)),
+                    None => Ok(None),
+                };
+            }
+            None => {
+                self.started = true;
+                true
+            }
+        };
+        let res = (if first { reader.check_input() } else { Ok(()) }).and_then(|()| {
+            let ev = reader.event()?;
+            let ty = match (reader.opts.typecheck, self.stack.last()) {
+                (false, _) => Type::Unknown,
+                (true, open) => {
+                    let expected = open.map_or(Type::Unknown, |open| open.ty.inner(open.read - 1));
+                    ev.check(&expected).map_err(|err| err.within(self.stack.iter().map(Open::seg)))?
+                }
+            };
+            let (first, len) = match &ev {
+                Event::Scalar(_) | Event::End => return Ok(ev),
+                Event::SomeStart { .. } => (PathSeg::Some, 1),
+                Event::AliasStart { id } => (PathSeg::Alias(*id), 1),
+                Event::EnumStart { id, ev } => (PathSeg::Variant(*id, *ev), 1),
+                Event::ListStart { len, .. } => (PathSeg::Index(SeqKind::List, 0), *len),
+                Event::TupleStart { len } => (PathSeg::Index(SeqKind::Tuple, 0), *len),
+                Event::StructStart { id, len } => (PathSeg::Index(SeqKind::Struct(*id), 0), *len),
+                Event::MapStart { len, .. } => (PathSeg::Key(0), len.checked_mul(2).ok_or(Error::TooLongLen(*len as u64))?),
+            };
+            reader.enter()?;
+            self.stack.push(Open { first, len, read: 0, ty });
+            Ok(ev)
+        });
+        match res {
+            Ok(ev) => Ok(Some(ev)),
+            Err(err) => match self.reader.take() {
+                Some(mut reader) => {
+                    let ty = reader.in_ty.then_some(PathSeg::Type);
+                    reader.path = self.stack.iter().map(Open::seg).chain(ty).collect::<Vec<_>>().into();
+                    reader.finish_with(Err(err))
+                }
+                None => Ok(None),
+            },
+        }
+    }
+
+    // skips to the end of the innermost open container, consuming its End
+    pub fn skip_rest(&mut self) -> FullResult<(), B> {
+        let depth = self.depth();
+        while self.depth() >= depth {
+            if self.next_event()?.is_none() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<B: AsRef<[u8]> + ByteStorage, I: Input<Storage = B>> Iterator for StreamReader<I> {
+    type Item = FullResult<Event<B>, B>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
 }
@@ -0,0 +1,140 @@
+use alloc::{collections::{btree_map, BTreeMap}, string::String};
+use super::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+    pub id: EnumVariantId,
+    pub name: String,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CVariant {
+    pub id: EnumVariantId,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Def {
+    Alias { name: String, ty: Type },
+    // fields in encoding order
+    Struct { name: String, fields: Vec<Field> },
+    Enum { name: String, variants: Vec<Variant> },
+    CEnum { name: String, variants: Vec<CVariant> },
+}
+
+impl Def {
+    pub fn name(&self) -> &str {
+        match self {
+            Def::Alias { name, .. }
+            | Def::Struct { name, .. }
+            | Def::Enum { name, .. }
+            | Def::CEnum { name, .. } => name,
+        }
+    }
+
+    pub const fn as_type(&self, id: TypeId) -> Type {
+        match self {
+            Def::Alias { .. } => Type::Alias(id),
+            Def::Struct { .. } => Type::Struct(id),
+            Def::Enum { .. } => Type::Enum(id),
+            Def::CEnum { .. } => Type::CEnum(id),
+        }
+    }
+}
+
+// definitions of named types, values of types not in the registry are only checked against their declared types
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Registry {
+    defs: BTreeMap<TypeId, Def>,
+}
+
+impl Registry {
+    pub const fn new() -> Registry {
+        Registry { defs: BTreeMap::new() }
+    }
+
+    // returns the replaced definition
+    pub fn insert(&mut self, id: TypeId, def: Def) -> Option<Def> {
+        self.defs.insert(id, def)
+    }
+
+    pub fn remove(&mut self, id: &TypeId) -> Option<Def> {
+        self.defs.remove(id)
+    }
+
+    pub fn get(&self, id: &TypeId) -> Option<&Def> {
+        self.defs.get(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.defs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.defs.is_empty()
+    }
+
+    pub fn iter(&self) -> btree_map::Iter<'_, TypeId, Def> {
+        self.defs.iter()
+    }
+
+    pub fn alias(&self, id: &TypeId) -> Option<&Type> {
+        match self.get(id)? {
+            Def::Alias { ty, .. } => Some(ty),
+            _ => None,
+        }
+    }
+
+    pub fn fields(&self, id: &TypeId) -> Option<&[Field]> {
+        match self.get(id)? {
+            Def::Struct { fields, .. } => Some(fields),
+            _ => None,
+        }
+    }
+
+    pub fn field(&self, id: &TypeId, index: usize) -> Option<&Field> {
+        self.fields(id)?.get(index)
+    }
+
+    pub fn variant(&self, id: &TypeId, ev: EnumVariantId) -> Option<&Variant> {
+        match self.get(id)? {
+            Def::Enum { variants, .. } => variants.iter().find(|v| v.id == ev),
+            _ => None,
+        }
+    }
+
+    pub fn c_variant(&self, id: &TypeId, ev: EnumVariantId) -> Option<&CVariant> {
+        match self.get(id)? {
+            Def::CEnum { variants, .. } => variants.iter().find(|v| v.id == ev),
+            _ => None,
+        }
+    }
+
+    // like `Value::check`, but also checks values of registered types against their definitions
+    pub fn check<B>(&self, val: &Value<B>, t: &Type) -> CheckResult<()> {
+        val.check_impl(t, Some(self))
+    }
+
+    pub fn typecheck<B>(&self, val: &Value<B>) -> CheckResult<()> {
+        val.check_impl(&Type::Unknown, Some(self))
+    }
+}
+
+impl FromIterator<(TypeId, Def)> for Registry {
+    fn from_iter<T: IntoIterator<Item = (TypeId, Def)>>(iter: T) -> Registry {
+        Registry { defs: iter.into_iter().collect() }
+    }
+}
+
+impl Extend<(TypeId, Def)> for Registry {
+    fn extend<T: IntoIterator<Item = (TypeId, Def)>>(&mut self, iter: T) {
+        self.defs.extend(iter)
+    }
+}
@@ -0,0 +1,317 @@
+use alloc::{collections::BTreeMap, string::String};
+use super::*;
+
+// Storages that byte vectors can be moved into when serializing.
+// Borrowed storages can't hold them, so `Vec<u8>` and `[u8; N]` are `List(U8)` there, in both the type and the value;
+// both forms are accepted when deserializing.
+pub trait Storage: AsRef<[u8]> + Sized {
+    // whether `from_vec` succeeds
+    const OWNED: bool;
+
+    // gives the vec back if not owned
+    fn from_vec(v: Vec<u8>) -> core::result::Result<Self, Vec<u8>>;
+}
+
+impl Storage for &[u8] {
+    const OWNED: bool = false;
+
+    fn from_vec(v: Vec<u8>) -> core::result::Result<Self, Vec<u8>> {
+        Err(v)
+    }
+}
+
+impl Storage for Vec<u8> {
+    const OWNED: bool = true;
+
+    fn from_vec(v: Vec<u8>) -> core::result::Result<Self, Vec<u8>> {
+        Ok(v)
+    }
+}
+
+impl Storage for Box<[u8]> {
+    const OWNED: bool = true;
+
+    fn from_vec(v: Vec<u8>) -> core::result::Result<Self, Vec<u8>> {
+        Ok(v.into_boxed_slice())
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl Storage for Bytes {
+    const OWNED: bool = true;
+
+    fn from_vec(v: Vec<u8>) -> core::result::Result<Self, Vec<u8>> {
+        Ok(v.into())
+    }
+}
+
+macro_rules! scalar_impl {
+    ($($ty:ty => $variant:ident $into:ident)*) => {$(
+        impl<B> Schema<B> for $ty {
+            const ID: TypeId = TypeId::Anonymous;
+
+            fn ty() -> Type {
+                Type::$variant
+            }
+
+            fn serialize(self) -> Value<B> {
+                Value::$variant(self)
+            }
+
+            fn deserialize(val: Value<B>) -> SchemaResult<Self> {
+                val.$into()
+            }
+        }
+    )*};
+}
+
+scalar_impl! {
+    bool => Bool into_bool
+    u16  => U16 into_u16
+    u32  => U32 into_u32
+    u64  => U64 into_u64
+    u128 => U128 into_u128
+    i8   => I8 into_i8
+    i16  => I16 into_i16
+    i32  => I32 into_i32
+    i64  => I64 into_i64
+    i128 => I128 into_i128
+    char => Char into_char
+}
+
+macro_rules! float_impl {
+    ($($ty:ty => $variant:ident $into:ident)*) => {$(
+        impl<B> Schema<B> for $ty {
+            const ID: TypeId = TypeId::Anonymous;
+
+            fn ty() -> Type {
+                Type::$variant
+            }
+
+            fn serialize(self) -> Value<B> {
+                Value::$variant(self.to_bits())
+            }
+
+            fn deserialize(val: Value<B>) -> SchemaResult<Self> {
+                Ok(<$ty>::from_bits(val.$into()?))
+            }
+        }
+    )*};
+}
+
+float_impl! {
+    f32 => F32 into_f32
+    f64 => F64 into_f64
+}
+
+impl<B: Storage> Schema<B> for u8 {
+    const ID: TypeId = TypeId::Anonymous;
+
+    fn ty() -> Type {
+        Type::U8
+    }
+
+    fn serialize(self) -> Value<B> {
+        Value::U8(self)
+    }
+
+    fn deserialize(val: Value<B>) -> SchemaResult<Self> {
+        val.into_u8()
+    }
+
+    fn __vec_ty() -> Type {
+        match B::OWNED {
+            true => Type::Bytes,
+            false => Type::List(Box::new(Type::U8)),
+        }
+    }
+
+    fn __serialize_vec(v: Vec<Self>) -> Value<B> {
+        match B::from_vec(v) {
+            Ok(b) => Value::Bytes(b),
+            Err(v) => Value::List(Type::U8, v.into_iter().map(Value::U8).collect()),
+        }
+    }
+
+    fn __deserialize_vec(val: Value<B>) -> SchemaResult<Vec<Self>> {
+        match val {
+            Value::Bytes(b) => Ok(b.as_ref().to_vec()),
+            Value::List(..) => val.into_list()?
+                .into_vec()
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| v.into_u8().map_err(|e| e.at(PathSeg::Index(SeqKind::List, i))))
+                .collect(),
+            val => Err(SchemaError::tag(Tag::Bytes, val.as_tag())),
+        }
+    }
+}
+
+impl<B> Schema<B> for () {
+    const ID: TypeId = TypeId::Anonymous;
+
+    fn ty() -> Type {
+        Type::Unit
+    }
+
+    fn serialize(self) -> Value<B> {
+        Value::Unit
+    }
+
+    fn deserialize(val: Value<B>) -> SchemaResult<Self> {
+        val.into_unit()
+    }
+}
+
+impl<B: AsRef<[u8]> + ByteStorage> Schema<B> for String where ByteStr<B>: From<String> {
+    const ID: TypeId = TypeId::Anonymous;
+
+    fn ty() -> Type {
+        Type::String
+    }
+
+    fn serialize(self) -> Value<B> {
+        Value::String(self.into())
+    }
+
+    fn deserialize(val: Value<B>) -> SchemaResult<Self> {
+        let s = val.into_string()?;
+        Ok(String::from(AsRef::<str>::as_ref(&s)))
+    }
+}
+
+impl<B, T: Schema<B>> Schema<B> for Option<T> {
+    const ID: TypeId = TypeId::Anonymous;
+
+    fn ty() -> Type {
+        Type::Option(Box::new(T::ty()))
+    }
+
+    fn serialize(self) -> Value<B> {
+        Value::Option(T::ty(), Box::new(self.map(T::serialize)))
+    }
+
+    fn deserialize(val: Value<B>) -> SchemaResult<Self> {
+        val.into_option()?
+            .map(|v| T::deserialize(v).map_err(|e| e.at(PathSeg::Some)))
+            .transpose()
+    }
+}
+
+impl<B, T: Schema<B>> Schema<B> for Vec<T> {
+    const ID: TypeId = TypeId::Anonymous;
+
+    fn ty() -> Type {
+        T::__vec_ty()
+    }
+
+    fn serialize(self) -> Value<B> {
+        T::__serialize_vec(self)
+    }
+
+    fn deserialize(val: Value<B>) -> SchemaResult<Self> {
+        T::__deserialize_vec(val)
+    }
+}
+
+// same as `Vec<T>` but with the length checked
+impl<B, T: Schema<B>, const N: usize> Schema<B> for [T; N] {
+    const ID: TypeId = TypeId::Anonymous;
+
+    fn ty() -> Type {
+        T::__vec_ty()
+    }
+
+    fn serialize(self) -> Value<B> {
+        T::__serialize_vec(self.into())
+    }
+
+    fn deserialize(val: Value<B>) -> SchemaResult<Self> {
+        let v = T::__deserialize_vec(val)?;
+        let found = v.len();
+        v.try_into().map_err(|_| SchemaError::new(SchemaErrorKind::Len { expected: N, found }))
+    }
+}
+
+impl<B, K: Schema<B> + Ord, V: Schema<B>> Schema<B> for BTreeMap<K, V> {
+    const ID: TypeId = TypeId::Anonymous;
+
+    fn ty() -> Type {
+        Type::Map(Box::new(K::ty()), Box::new(V::ty()))
+    }
+
+    fn serialize(self) -> Value<B> {
+        Value::Map(
+            (K::ty(), V::ty()),
+            self.into_iter().map(|(k, v)| (k.serialize(), v.serialize())).collect(),
+        )
+    }
+
+    fn deserialize(val: Value<B>) -> SchemaResult<Self> {
+        val.into_map()?
+            .into_vec()
+            .into_iter()
+            .enumerate()
+            .map(|(i, (k, v))| Ok((
+                K::deserialize(k).map_err(|e| e.at(PathSeg::Key(i)))?,
+                V::deserialize(v).map_err(|e| e.at(PathSeg::Value(i)))?,
+            )))
+            .collect()
+    }
+}
+
+// transparent
+impl<B, T: Schema<B>> Schema<B> for Box<T> {
+    const ID: TypeId = T::ID;
+
+    fn ty() -> Type {
+        T::ty()
+    }
+
+    fn serialize(self) -> Value<B> {
+        (*self).serialize()
+    }
+
+    fn deserialize(val: Value<B>) -> SchemaResult<Self> {
+        T::deserialize(val).map(Box::new)
+    }
+}
+
+macro_rules! tuple_impl {
+    ($($len:literal => ($($name:ident $idx:literal)+))*) => {$(
+        impl<B, $($name: Schema<B>),+> Schema<B> for ($($name,)+) {
+            const ID: TypeId = TypeId::Anonymous;
+
+            fn ty() -> Type {
+                Type::Tuple(Box::new([$($name::ty()),+]))
+            }
+
+            #[allow(non_snake_case)]
+            fn serialize(self) -> Value<B> {
+                let ($($name,)+) = self;
+                Value::Tuple(Box::new([$($name.serialize()),+]))
+            }
+
+            #[allow(non_snake_case)]
+            fn deserialize(val: Value<B>) -> SchemaResult<Self> {
+                let [$($name),+] = val.into_tuple_of::<$len>()?;
+                Ok(($($name::deserialize($name).map_err(|e| e.at(PathSeg::Index(SeqKind::Tuple, $idx)))?,)+))
+            }
+        }
+    )*};
+}
+
+tuple_impl! {
+    1 => (T0 0)
+    2 => (T0 0 T1 1)
+    3 => (T0 0 T1 1 T2 2)
+    4 => (T0 0 T1 1 T2 2 T3 3)
+    5 => (T0 0 T1 1 T2 2 T3 3 T4 4)
+    6 => (T0 0 T1 1 T2 2 T3 3 T4 4 T5 5)
+    7 => (T0 0 T1 1 T2 2 T3 3 T4 4 T5 5 T6 6)
+    8 => (T0 0 T1 1 T2 2 T3 3 T4 4 T5 5 T6 6 T7 7)
+    9 => (T0 0 T1 1 T2 2 T3 3 T4 4 T5 5 T6 6 T7 7 T8 8)
+    10 => (T0 0 T1 1 T2 2 T3 3 T4 4 T5 5 T6 6 T7 7 T8 8 T9 9)
+    11 => (T0 0 T1 1 T2 2 T3 3 T4 4 T5 5 T6 6 T7 7 T8 8 T9 9 T10 10)
+    12 => (T0 0 T1 1 T2 2 T3 3 T4 4 T5 5 T6 6 T7 7 T8 8 T9 9 T10 10 T11 11)
+}
@@ -0,0 +1,391 @@
+use alloc::{format, string::{String, ToString}};
+use serde::ser::{self, Serialize};
+use super::*;
+
+// Mapping of the serde data model, mirroring the derive macro where possible:
+// - structs, tuple structs and unit structs => `Struct` with the configured id
+// - newtype structs => `Alias` with the configured id
+// - unit variants => `CEnum`
+// - newtype, tuple and struct variants => `Enum` with the field, a `Tuple` or an anonymous `Struct` as payload
+// - seqs => `List`, tuples => `Tuple`, maps => `Map`
+// Types of lists, maps and options are the most specific types matching all inner values, `Unknown` if none.
+// Struct fields are positional, so `skip_serializing_if` and `flatten` can't be decoded.
+
+pub type SerializeResult = SerdeResult<Value<Vec<u8>>>;
+
+impl SerdeOptions {
+    pub const DEFAULT: SerdeOptions = SerdeOptions {
+        opts: Options::DEFAULT,
+        ids: anonymous_ids,
+    };
+}
+
+impl Default for SerdeOptions {
+    fn default() -> Self {
+        SerdeOptions::DEFAULT
+    }
+}
+
+pub fn anonymous_ids(_name: &'static str) -> TypeId {
+    TypeId::Anonymous
+}
+
+// ids from the type names, which are not qualified so may collide
+pub fn hash_ids(name: &'static str) -> TypeId {
+    TypeId::Hash(HashId::from_path(name))
+}
+
+impl SerdeError {
+    pub const fn new(kind: SerdeErrorKind) -> SerdeError {
+        SerdeError { path: Path::new(), kind }
+    }
+
+    pub fn at(mut self, seg: PathSeg) -> SerdeError {
+        self.path.0.insert(0, seg);
+        self
+    }
+}
+
+impl From<SchemaError> for SerdeError {
+    fn from(e: SchemaError) -> SerdeError {
+        SerdeError { path: e.path, kind: SerdeErrorKind::Schema(e.kind) }
+    }
+}
+
+impl From<EncodeError> for SerdeError {
+    fn from(e: EncodeError) -> SerdeError {
+        SerdeError::new(SerdeErrorKind::Encode(e))
+    }
+}
+
+impl core::fmt::Display for SerdeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.kind {
+            SerdeErrorKind::Custom(msg) => f.write_str(msg)?,
+            kind => write!(f, "{:?}", kind)?,
+        }
+        if !self.path.is_root() {
+            write!(f, " at {}", self.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl ser::StdError for SerdeError {}
+
+impl ser::Error for SerdeError {
+    fn custom<T: core::fmt::Display>(msg: T) -> SerdeError {
+        SerdeError::new(SerdeErrorKind::Custom(msg.to_string()))
+    }
+}
+
+pub fn to_value<T: Serialize + ?Sized>(val: &T) -> SerializeResult {
+    to_value_with(val, SerdeOptions::DEFAULT)
+}
+
+pub fn to_value_with<T: Serialize + ?Sized>(val: &T, opts: SerdeOptions) -> SerializeResult {
+    val.serialize(Serializer { ids: opts.ids })
+}
+
+pub fn to_vec<T: Serialize + ?Sized>(val: &T) -> SerdeResult<Vec<u8>> {
+    to_vec_with(val, SerdeOptions::DEFAULT)
+}
+
+pub fn to_vec_with<T: Serialize + ?Sized>(val: &T, opts: SerdeOptions) -> SerdeResult<Vec<u8>> {
+    Ok(to_value_with(val, opts)?.encode_with::<VecOutput>(opts.opts)?)
+}
+
+// the most specific type of all values
+fn meet_all<'a, B: 'a>(vals: impl Iterator<Item = &'a Value<B>>) -> Type {
+    let mut t = Type::Unknown;
+    for v in vals {
+        match t.meet(&v.as_type()) {
+            Some(m) => t = m,
+            None => return Type::Unknown,
+        }
+    }
+    t
+}
+
+#[derive(Clone, Copy)]
+pub struct Serializer {
+    ids: fn(&'static str) -> TypeId,
+}
+
+impl Serializer {
+    pub const fn new(opts: SerdeOptions) -> Serializer {
+        Serializer { ids: opts.ids }
+    }
+}
+
+macro_rules! scalar_impl {
+    ($($fn_name:ident($ty:ty) => $variant:ident)*) => {$(
+        fn $fn_name(self, v: $ty) -> SerializeResult {
+            Ok(Value::$variant(v))
+        }
+    )*};
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Value<Vec<u8>>;
+    type Error = SerdeError;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = SeqSerializer;
+    type SerializeStructVariant = SeqSerializer;
+
+    scalar_impl! {
+        serialize_bool(bool) => Bool
+        serialize_u8(u8) => U8
+        serialize_u16(u16) => U16
+        serialize_u32(u32) => U32
+        serialize_u64(u64) => U64
+        serialize_u128(u128) => U128
+        serialize_i8(i8) => I8
+        serialize_i16(i16) => I16
+        serialize_i32(i32) => I32
+        serialize_i64(i64) => I64
+        serialize_i128(i128) => I128
+        serialize_char(char) => Char
+    }
+
+    fn serialize_f32(self, v: f32) -> SerializeResult {
+        Ok(Value::F32(v.to_bits()))
+    }
+
+    fn serialize_f64(self, v: f64) -> SerializeResult {
+        Ok(Value::F64(v.to_bits()))
+    }
+
+    fn serialize_str(self, v: &str) -> SerializeResult {
+        Ok(Value::String(String::from(v).into()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> SerializeResult {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> SerializeResult {
+        Ok(Value::Option(Type::Unknown, Box::new(None)))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, v: &T) -> SerializeResult {
+        let v = v.serialize(self)?;
+        Ok(Value::Option(v.as_type(), Box::new(Some(v))))
+    }
+
+    fn serialize_unit(self) -> SerializeResult {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> SerializeResult {
+        Ok(Value::Struct((self.ids)(name), Box::new([])))
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, ev: u32, _variant: &'static str) -> SerializeResult {
+        Ok(Value::CEnum((self.ids)(name), ev.into()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, name: &'static str, v: &T) -> SerializeResult {
+        Ok(Value::Alias((self.ids)(name), Box::new(v.serialize(self)?)))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        ev: u32,
+        _variant: &'static str,
+        v: &T,
+    ) -> SerializeResult {
+        Ok(Value::Enum((self.ids)(name), ev.into(), Box::new(v.serialize(self)?)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> SerdeResult<SeqSerializer> {
+        Ok(SeqSerializer::new(self, SeqTarget::List, len.unwrap_or(0)))
+    }
+
+    fn serialize_tuple(self, len: usize) -> SerdeResult<SeqSerializer> {
+        Ok(SeqSerializer::new(self, SeqTarget::Tuple, len))
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> SerdeResult<SeqSerializer> {
+        Ok(SeqSerializer::new(self, SeqTarget::Struct((self.ids)(name)), len))
+    }
+
+    fn serialize_tuple_variant(self, name: &'static str, ev: u32, _variant: &'static str, len: usize) -> SerdeResult<SeqSerializer> {
+        Ok(SeqSerializer::new(self, SeqTarget::TupleVariant((self.ids)(name), ev.into()), len))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> SerdeResult<MapSerializer> {
+        Ok(MapSerializer { ser: self, entries: Vec::with_capacity(len.unwrap_or(0)), key: None })
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> SerdeResult<SeqSerializer> {
+        Ok(SeqSerializer::new(self, SeqTarget::Struct((self.ids)(name)), len))
+    }
+
+    fn serialize_struct_variant(self, name: &'static str, ev: u32, _variant: &'static str, len: usize) -> SerdeResult<SeqSerializer> {
+        Ok(SeqSerializer::new(self, SeqTarget::StructVariant((self.ids)(name), ev.into()), len))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+enum SeqTarget {
+    List,
+    Tuple,
+    Struct(TypeId),
+    TupleVariant(TypeId, EnumVariantId),
+    StructVariant(TypeId, EnumVariantId),
+}
+
+pub struct SeqSerializer {
+    ser: Serializer,
+    target: SeqTarget,
+    items: Vec<Value<Vec<u8>>>,
+}
+
+impl SeqSerializer {
+    fn new(ser: Serializer, target: SeqTarget, len: usize) -> SeqSerializer {
+        SeqSerializer { ser, target, items: Vec::with_capacity(len) }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, v: &T) -> SerdeResult<()> {
+        self.items.push(v.serialize(self.ser)?);
+        Ok(())
+    }
+
+    fn finish(self) -> SerializeResult {
+        let items = self.items.into_boxed_slice();
+        Ok(match self.target {
+            SeqTarget::List => Value::List(meet_all(items.iter()), items),
+            SeqTarget::Tuple => Value::Tuple(items),
+            SeqTarget::Struct(id) => Value::Struct(id, items),
+            SeqTarget::TupleVariant(id, ev) => Value::Enum(id, ev, Box::new(Value::Tuple(items))),
+            SeqTarget::StructVariant(id, ev) => Value::Enum(id, ev, Box::new(Value::Struct(TypeId::Anonymous, items))),
+        })
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value<Vec<u8>>;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, v: &T) -> SerdeResult<()> {
+        self.push(v)
+    }
+
+    fn end(self) -> SerializeResult {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value<Vec<u8>>;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, v: &T) -> SerdeResult<()> {
+        self.push(v)
+    }
+
+    fn end(self) -> SerializeResult {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value<Vec<u8>>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, v: &T) -> SerdeResult<()> {
+        self.push(v)
+    }
+
+    fn end(self) -> SerializeResult {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok = Value<Vec<u8>>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, v: &T) -> SerdeResult<()> {
+        self.push(v)
+    }
+
+    fn end(self) -> SerializeResult {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SeqSerializer {
+    type Ok = Value<Vec<u8>>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, v: &T) -> SerdeResult<()> {
+        self.push(v)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> SerdeResult<()> {
+        Err(<SerdeError as ser::Error>::custom(format!("skipped field `{}` can't be encoded positionally", key)))
+    }
+
+    fn end(self) -> SerializeResult {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SeqSerializer {
+    type Ok = Value<Vec<u8>>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, v: &T) -> SerdeResult<()> {
+        self.push(v)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> SerdeResult<()> {
+        Err(<SerdeError as ser::Error>::custom(format!("skipped field `{}` can't be encoded positionally", key)))
+    }
+
+    fn end(self) -> SerializeResult {
+        self.finish()
+    }
+}
+
+type Entry = (Value<Vec<u8>>, Value<Vec<u8>>);
+
+pub struct MapSerializer {
+    ser: Serializer,
+    entries: Vec<Entry>,
+    // waiting for its value
+    key: Option<Value<Vec<u8>>>,
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Value<Vec<u8>>;
+    type Error = SerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, k: &T) -> SerdeResult<()> {
+        self.key = Some(k.serialize(self.ser)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, v: &T) -> SerdeResult<()> {
+        let k = self.key.take().ok_or_else(|| <SerdeError as ser::Error>::custom("value without key"))?;
+        self.entries.push((k, v.serialize(self.ser)?));
+        Ok(())
+    }
+
+    fn end(self) -> SerializeResult {
+        let entries = self.entries.into_boxed_slice();
+        let tk = meet_all(entries.iter().map(|(k, _)| k));
+        let tv = meet_all(entries.iter().map(|(_, v)| v));
+        Ok(Value::Map((tk, tv), entries))
+    }
+}
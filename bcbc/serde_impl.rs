@@ -0,0 +1,141 @@
+// serde impls of `Value`, `Type` and `TypeId` themselves, for transcoding to other formats.
+// Enums are externally tagged as derived by serde, so in JSON:
+// - `Type`: `"U8"`, `{"Option":"U8"}`, `{"Map":["String","U8"]}`, `{"Tuple":["Bool","Unit"]}`,
+//   `{"Struct":{"Std":{"schema":1,"id":16}}}`
+// - `TypeId`: `"Anonymous"`, `{"Std":{"schema":1,"id":16}}`, `{"Hash":"00112233445566"}` with the 7 bytes of
+//   the hash as hex in human-readable formats, and as bytes otherwise
+// - `Value`: `"Unit"`, `{"U8":1}`, `{"String":"abc"}`, `{"Bytes":[1,2]}` (as serde bytes), and floats as their
+//   bit patterns, e.g. `{"F16":15360}` for 1.0
+// - `Option`s carry their type: `{"Option":["U8",{"U8":1}]}`, `{"Option":["U8",null]}`
+// - `List`s and `Map`s likewise: `{"List":["U8",[{"U8":1}]]}`, `{"Map":[["U8","Bool"],[[{"U8":1},{"Bool":true}]]]}`
+// - `CEnum`, `Enum` and `Struct` are `[id, ev]`, `[id, ev, value]` and `[id, [values..]]`
+// Deserializing `Value<B>` needs an owned storage for bytes, see `Storage`.
+
+use alloc::vec::Vec;
+use core::fmt;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use super::*;
+
+pub(crate) mod bytes {
+    use super::*;
+
+    pub fn serialize<B: AsRef<[u8]>, S: Serializer>(bytes: &B, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(bytes.as_ref())
+    }
+
+    pub fn deserialize<'de, B: Storage, D: Deserializer<'de>>(deserializer: D) -> core::result::Result<B, D::Error> {
+        let v = deserializer.deserialize_byte_buf(BytesVisitor)?;
+        B::from_vec(v).map_err(|_| de::Error::custom("borrowed storage can't hold deserialized bytes"))
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> de::Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("bytes")
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> core::result::Result<Vec<u8>, E> {
+            Ok(v.to_vec())
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> core::result::Result<Vec<u8>, E> {
+            Ok(v)
+        }
+
+        // formats without bytes, e.g. JSON
+        fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> core::result::Result<Vec<u8>, A::Error> {
+            let mut v = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+            while let Some(b) = seq.next_element()? {
+                v.push(b);
+            }
+            Ok(v)
+        }
+    }
+}
+
+// checks the reserved schemas
+#[derive(Deserialize)]
+#[serde(rename = "StdId")]
+pub(crate) struct StdIdRepr {
+    schema: u8,
+    id: u16,
+}
+
+impl TryFrom<StdIdRepr> for StdId {
+    type Error = &'static str;
+
+    fn try_from(StdIdRepr { schema, id }: StdIdRepr) -> core::result::Result<StdId, &'static str> {
+        StdId::from_inner(schema, id).ok_or("schema 0x00 and 0xff are reserved")
+    }
+}
+
+const HEX: &[u8; 16] = b"0123456789abcdef";
+
+impl Serialize for HashId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            let mut buf = [0; 14];
+            for (i, b) in self.hash.iter().enumerate() {
+                buf[i * 2] = HEX[(b >> 4) as usize];
+                buf[i * 2 + 1] = HEX[(b & 0xf) as usize];
+            }
+            serializer.serialize_str(core::str::from_utf8(&buf).unwrap())
+        } else {
+            serializer.serialize_bytes(&self.hash)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for HashId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<HashId, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(HashIdVisitor)
+        } else {
+            deserializer.deserialize_bytes(HashIdVisitor)
+        }
+    }
+}
+
+struct HashIdVisitor;
+
+impl<'de> de::Visitor<'de> for HashIdVisitor {
+    type Value = HashId;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("7 bytes or 14 hex digits")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> core::result::Result<HashId, E> {
+        fn digit(c: u8) -> Option<u8> {
+            (c as char).to_digit(16).map(|d| d as u8)
+        }
+        let v = v.as_bytes();
+        if v.len() != 14 {
+            return Err(E::invalid_length(v.len(), &self));
+        }
+        let mut hash = [0; 7];
+        for (i, b) in hash.iter_mut().enumerate() {
+            let (Some(h), Some(l)) = (digit(v[i * 2]), digit(v[i * 2 + 1])) else {
+                return Err(E::custom("invalid hex digit"));
+            };
+            *b = (h << 4) | l;
+        }
+        Ok(HashId { hash })
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> core::result::Result<HashId, E> {
+        let hash = v.try_into().map_err(|_| E::invalid_length(v.len(), &self))?;
+        Ok(HashId { hash })
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> core::result::Result<HashId, A::Error> {
+        let mut hash = [0; 7];
+        for (i, b) in hash.iter_mut().enumerate() {
+            *b = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        Ok(HashId { hash })
+    }
+}
@@ -1,3 +1,4 @@
+use alloc::{vec, vec::Vec};
 use hex_literal::hex;
 use crate::*;
 
@@ -31,7 +32,7 @@ macro_rules! println {
 fn cases() {
     fn case(v: Value<&'static [u8]>, exp: &'static [u8]) {
         println!("{:?}", &v);
-        let buf = v.encode::<VecOutput>();
+        let buf = v.encode::<VecOutput>().unwrap();
         println!("len={}", exp.len());
         println!("{}", hex::encode(&exp));
         println!("len={}", buf.len());
@@ -114,9 +115,27 @@ fn cases() {
         "),
     );
 
+    case(
+        Value::Tuple(seq![
+            Value::Char('a'),
+            Value::Char('\u{1f980}'),
+            Value::U128(u128::MAX),
+            Value::I128(-2),
+            Value::Type(Type::Map(Box::new(Type::Char), Box::new(Type::Tuple(seq![Type::U128, Type::I128])))),
+        ]),
+        expb!("
+        c5
+        0f 00000061
+        0f 0001f980
+        1f ffffffffffffffffffffffffffffffff
+        2f fffffffffffffffffffffffffffffffe
+        6e 12 1a 13 02 1b 1c
+        "),
+    );
+
     fn err_case(exp: &'static [u8], err: Error, pos: usize) {
         let err2 = Value::decode::<SliceInput>(&exp).unwrap_err();
-        assert_eq!(err2, FullError { err, buf: exp, pos });
+        assert_eq!(err2, FullError { err, buf: exp, pos, path: Path::new() });
     }
 
     err_case(
@@ -137,10 +156,11 @@ fn cases() {
         1,
     );
 
-    err_case(
-        expb!("6e ff"),
-        Error::Tag(0xff),
-        2,
+    // in the payload of a `Type` value
+    let buf = expb!("6e ff");
+    assert_eq!(
+        Value::decode::<SliceInput>(buf).unwrap_err(),
+        FullError { err: Error::Tag(0xff), buf, pos: 2, path: Path(vec![PathSeg::Type]) },
     );
 
     err_case(
@@ -172,4 +192,1497 @@ fn cases() {
         Error::BytevarNegZero { buf: [0; 8] },
         2,
     );
+    err_case(
+        expb!("0f 0000d800"),
+        Error::Char(0xd800),
+        5,
+    );
+
+    for code in 0x3..=0x7 {
+        let exp = [code << 4 | 0xf];
+        let err = Value::decode::<SliceInput>(&exp).unwrap_err();
+        assert_eq!((err.err, err.pos), (Error::Ext2(code), 1));
+    }
+
+    err_case(
+        expb!("1f 00"),
+        Error::Read(ReadError::TooShort { rest: 1, expected: 16 }),
+        1,
+    );
+}
+
+#[test]
+fn stream() {
+    fn events(buf: &'static [u8]) -> core::result::Result<Vec<Event<&'static [u8]>>, FullError<&'static [u8]>> {
+        reader::StreamReader::<SliceInput>::new(buf).collect()
+    }
+
+    assert_eq!(
+        events(expb!("
+        b2 06 110e
+        03 7b     a2 0e 85 68656c6c6f   87 676f6f64627965
+        23 0f423f a1 0e 86 7468616e6b73
+        ")).unwrap(),
+        vec![
+            Event::MapStart { len: 2, tk: Type::U64, tv: Type::List(Box::new(Type::String)) },
+            Event::Scalar(Value::U64(123)),
+            Event::ListStart { len: 2, ty: Type::String },
+            Event::Scalar(Value::String(s("hello"))),
+            Event::Scalar(Value::String(s("goodbye"))),
+            Event::End,
+            Event::Scalar(Value::U64(999999)),
+            Event::ListStart { len: 1, ty: Type::String },
+            Event::Scalar(Value::String(s("thanks"))),
+            Event::End,
+            Event::End,
+        ],
+    );
+
+    assert_eq!(
+        events(expb!("
+        c3
+        4e 0e 85 68656c6c6f
+        3e 0e
+        e5 01 5f49 5e ff fedcba98765432 c0
+        ")).unwrap(),
+        vec![
+            Event::TupleStart { len: 3 },
+            Event::SomeStart { ty: Type::String },
+            Event::Scalar(Value::String(s("hello"))),
+            Event::End,
+            Event::Scalar(Value::Option(Type::String, Box::new(None))),
+            Event::EnumStart { id: TypeId::Std(StdId { schema: 0x01, id: 0x5f49 }), ev: 5 },
+            Event::AliasStart { id: TypeId::Hash(HashId { hash: hex!("fedcba98765432") }) },
+            Event::TupleStart { len: 0 },
+            Event::End,
+            Event::End,
+            Event::End,
+            Event::End,
+        ],
+    );
+
+    let mut reader = reader::StreamReader::<SliceInput>::new(expb!("c2 a2 0e 81 61 81 62 2e"));
+    assert_eq!(reader.next_event().unwrap(), Some(Event::TupleStart { len: 2 }));
+    assert_eq!(reader.next_event().unwrap(), Some(Event::ListStart { len: 2, ty: Type::String }));
+    reader.skip_rest().unwrap();
+    assert_eq!(reader.pos(), 7);
+    assert_eq!(reader.next_event().unwrap(), Some(Event::Scalar(Value::Bool(true))));
+    assert_eq!(reader.next_event().unwrap(), Some(Event::End));
+    assert_eq!(reader.next_event().unwrap(), None);
+
+    // same errors as decode
+    for exp in [
+        expb!("0e 000000"),
+        expb!("c2 89 426572796c736f66"),
+        expb!("a1 6e ff"),
+        expb!("c1 4e 0e 82 ffff"),
+    ] {
+        assert_eq!(events(exp).unwrap_err(), Value::decode::<SliceInput>(exp).unwrap_err());
+    }
+}
+
+#[test]
+fn stream_writer() {
+    let mut w = writer::StreamWriter::<VecOutput>::new();
+    w.begin_map(&Type::U64, &Type::List(Box::new(Type::String)), 2).unwrap();
+    w.u64(123).unwrap();
+    w.begin_list(&Type::String, 2).unwrap();
+    w.string("hello").unwrap();
+    w.string("goodbye").unwrap();
+    w.end().unwrap();
+    w.u64(999999).unwrap();
+    w.begin_list(&Type::String, 2).unwrap();
+    w.string("thanks").unwrap();
+    w.string("how are you").unwrap();
+    w.end().unwrap();
+    w.end().unwrap();
+    assert_eq!(w.finish().unwrap(), expb!("
+    b2 06 110e
+    03 7b     a2 0e 85 68656c6c6f   87 676f6f64627965
+    23 0f423f a2 0e 86 7468616e6b73 8b 686f772061726520796f75
+    "));
+
+    let mut w = writer::StreamWriter::<VecOutput>::new();
+    w.begin_struct(TypeId::Anonymous, 2).unwrap();
+    w.unit().unwrap();
+    assert_eq!(w.end(), Err(EncodeError::LenMismatch { len: 2, written: 1 }));
+
+    let mut w = writer::StreamWriter::<VecOutput>::new();
+    w.begin_list(&Type::Bool, 1).unwrap();
+    w.bool(true).unwrap();
+    assert_eq!(w.bool(false), Err(EncodeError::LenMismatch { len: 1, written: 2 }));
+    w.end().unwrap();
+    assert_eq!(w.end(), Err(EncodeError::UnexpectedEnd));
+    assert_eq!(w.unit(), Err(EncodeError::LenMismatch { len: 1, written: 2 }));
+
+    let mut w = writer::StreamWriter::<VecOutput>::new();
+    w.begin_some(&Type::Unit).unwrap();
+    assert_eq!(w.finish(), Err(EncodeError::Unfinished { depth: 1 }));
+
+    // piping
+    let buf = expb!("
+    c3
+    4e 0e 85 68656c6c6f
+    3e 0e
+    e5 01 5f49 5e ff fedcba98765432 c0
+    ");
+    let mut w = writer::StreamWriter::<VecOutput>::new();
+    for ev in reader::StreamReader::<SliceInput>::new(buf) {
+        w.event(&ev.unwrap()).unwrap();
+    }
+    assert_eq!(w.finish().unwrap(), buf);
+}
+
+#[test]
+fn encode_errors() {
+    use alloc::string::ToString;
+
+    // fails to write past `N` bytes
+    #[derive(Default)]
+    struct BoundedOutput<const N: usize> {
+        bytes: Vec<u8>,
+    }
+
+    impl<const N: usize> BoundedOutput<N> {
+        fn check(&self, expected: usize) -> core::result::Result<(), WriteError> {
+            let rest = N - self.bytes.len();
+            if expected > rest {
+                return Err(WriteError::Full { rest, expected });
+            }
+            Ok(())
+        }
+    }
+
+    impl<const N: usize> Output for BoundedOutput<N> {
+        type Storage = Vec<u8>;
+
+        fn byte(&mut self, n: u8) -> core::result::Result<(), WriteError> {
+            self.check(1)?;
+            self.bytes.push(n);
+            Ok(())
+        }
+
+        fn bytes<B: AsRef<[u8]>>(&mut self, bytes: B) -> core::result::Result<(), WriteError> {
+            self.check(bytes.as_ref().len())?;
+            self.bytes.extend_from_slice(bytes.as_ref());
+            Ok(())
+        }
+
+        fn leak(self) -> Vec<u8> {
+            self.bytes
+        }
+    }
+
+    let full = |rest, expected| Err(EncodeError::Write(WriteError::Full { rest, expected }));
+    assert_eq!(Value::String(s("abc")).encode::<BoundedOutput<4>>().unwrap(), expb!("83 616263"));
+    assert_eq!(Value::String(s("abcd")).encode::<BoundedOutput<4>>(), full(3, 4));
+
+    // at every point of a nested value
+    let v = Value::Tuple(seq![
+        Value::U8(1),
+        Value::List(Type::String, seq![Value::String(s("ab"))]),
+    ]);
+    let buf = v.encode::<VecOutput>().unwrap();
+    assert_eq!(buf, expb!("c2 0001 a1 0e 82 6162"));
+    assert_eq!(v.encode::<BoundedOutput<0>>(), full(0, 1));
+    assert_eq!(v.encode::<BoundedOutput<1>>(), full(0, 1));
+    assert_eq!(v.encode::<BoundedOutput<2>>(), full(0, 1));
+    assert_eq!(v.encode::<BoundedOutput<4>>(), full(0, 1));
+    assert_eq!(v.encode::<BoundedOutput<7>>(), full(1, 2));
+    assert_eq!(v.encode::<BoundedOutput<8>>().unwrap(), buf);
+
+    let mut w = writer::StreamWriter::<BoundedOutput<3>>::new();
+    w.begin_tuple(2).unwrap();
+    w.u8(1).unwrap();
+    assert_eq!(w.string("ab"), Err(EncodeError::Write(WriteError::Full { rest: 0, expected: 1 })));
+
+    let err = EncodeError::Write(WriteError::Full { rest: 0, expected: 1 });
+    assert_eq!(err.to_string(), "write error: output full: 1 bytes to write, 0 left");
+    assert_eq!(WriteError::Full { rest: 3, expected: 4 }.to_string(), "output full: 4 bytes to write, 3 left");
+    assert_eq!(EncodeError::LenMismatch { len: 1, written: 2 }.to_string(), "2 items written into a container of 1");
+    #[cfg(feature = "std")]
+    {
+        use std::error::Error as _;
+
+        assert_eq!(err.source().unwrap().to_string(), "output full: 1 bytes to write, 0 left");
+        assert!(EncodeError::UnexpectedEnd.source().is_none());
+    }
+
+    let v = Value::<&[u8]>::Type(Type::Tuple(vec![Type::Unit; 256].into()));
+    assert_eq!(v.encode::<VecOutput>(), Err(EncodeError::TooLongTuple(256)));
+}
+
+#[test]
+fn len_policy() {
+    const SIZE_MAX: usize = Options::DEFAULT.max_len as usize;
+    let long = vec![0; SIZE_MAX + 1];
+    let v = Value::Bytes(long.as_slice());
+    assert_eq!(v.check_encode(), Err(EncodeError::TooLongLen(SIZE_MAX as u64 + 1)));
+    assert_eq!(v.encode::<VecOutput>(), Err(EncodeError::TooLongLen(SIZE_MAX as u64 + 1)));
+
+    let v = Value::<&[u8]>::List(Type::Unit, vec![Value::Unit; SIZE_MAX + 1].into());
+    assert_eq!(v.check_encode(), Err(EncodeError::TooLongLen(SIZE_MAX as u64 + 1)));
+
+    let mut w = writer::StreamWriter::<VecOutput>::new();
+    assert_eq!(w.begin_tuple(SIZE_MAX + 1), Err(EncodeError::TooLongLen(SIZE_MAX as u64 + 1)));
+
+    let v = Value::Bytes(&long[..SIZE_MAX]);
+    v.check_encode().unwrap();
+    let buf = v.encode::<VecOutput>().unwrap();
+    assert_eq!(Value::decode::<SliceInput>(buf.as_slice()).unwrap(), v);
+}
+
+#[test]
+fn options() {
+    let opts = Options { max_depth: 2, ..Options::DEFAULT };
+    let buf = expb!("5e 00 5e 00 5e 00 2e");
+    let v = Value::decode_with::<SliceInput>(buf, Options::DEFAULT).unwrap();
+    assert_eq!(
+        Value::decode_with::<SliceInput>(buf, opts).unwrap_err(),
+        FullError { err: Error::TooDeep(3), buf, pos: 6, path: Path(vec![PathSeg::Alias(TypeId::Anonymous), PathSeg::Alias(TypeId::Anonymous)]) },
+    );
+    assert_eq!(v.encode_with::<VecOutput>(opts), Err(EncodeError::TooDeep(3)));
+    let opts = Options { max_depth: 3, ..Options::DEFAULT };
+    assert_eq!(v.encode_with::<VecOutput>(opts).unwrap(), buf);
+
+    // type annotations count
+    let buf = expb!("6e 10 10 02");
+    let opts = Options { max_depth: 1, ..Options::DEFAULT };
+    assert_eq!(
+        Value::decode_with::<SliceInput>(buf, opts).unwrap_err(),
+        FullError { err: Error::TooDeep(2), buf, pos: 3, path: Path(vec![PathSeg::Type]) },
+    );
+
+    let opts = Options { max_alloc: 3, ..Options::DEFAULT };
+    let buf = expb!("c2 c2 1e 1e 2e");
+    let v = Value::decode_with::<SliceInput>(buf, Options::DEFAULT).unwrap();
+    assert_eq!(
+        Value::decode_with::<SliceInput>(buf, opts).unwrap_err(),
+        FullError { err: Error::TooManyItems(4), buf, pos: 2, path: Path(vec![PathSeg::Index(SeqKind::Tuple, 0)]) },
+    );
+    assert_eq!(v.encode_with::<VecOutput>(opts), Err(EncodeError::TooManyItems(4)));
+
+    let opts = Options { max_input: 4, ..Options::DEFAULT };
+    let buf = expb!("84 61626364");
+    let v = Value::decode_with::<SliceInput>(buf, Options::DEFAULT).unwrap();
+    assert_eq!(
+        Value::decode_with::<SliceInput>(buf, opts).unwrap_err(),
+        FullError { err: Error::TooLongInput(5), buf, pos: 0, path: Path::new() },
+    );
+    assert_eq!(v.encode_with::<VecOutput>(opts), Ok(buf.to_vec()));
+    let opts = Options { max_output: 4, ..Options::DEFAULT };
+    assert_eq!(v.encode_with::<VecOutput>(opts), Err(EncodeError::TooLongOutput(5)));
+    assert_eq!(Value::decode_with::<SliceInput>(buf, opts).unwrap(), v);
+
+    let long = vec![0; 70000];
+    let v = Value::Bytes(long.as_slice());
+    let buf = v.encode_with::<VecOutput>(Options::TRUSTED).unwrap();
+    assert_eq!(
+        Value::decode::<SliceInput>(buf.as_slice()).unwrap_err().err,
+        Error::TooLongLen(70000),
+    );
+    assert_eq!(Value::decode_with::<SliceInput>(buf.as_slice(), Options::TRUSTED).unwrap(), v);
+}
+
+#[test]
+fn deep() {
+    use alloc::format;
+
+    const DEPTH: usize = Options::TRUSTED.max_depth;
+    let mut buf = vec![0xc1; DEPTH];
+    buf.push(0x0e);
+    let v = Value::decode_with::<SliceInput>(buf.as_slice(), Options::TRUSTED).unwrap();
+    assert_eq!(v.encode_with::<VecOutput>(Options::TRUSTED).unwrap(), buf);
+    assert_eq!(
+        Value::decode::<SliceInput>(buf.as_slice()).unwrap_err().err,
+        Error::TooDeep(129),
+    );
+    assert_eq!(v.check_encode(), Err(EncodeError::TooDeep(129)));
+    // the recursive impls are fine at the limit
+    assert_eq!(v.clone(), v);
+    assert_eq!(format!("{:?}", v).matches("Tuple").count(), DEPTH);
+    drop(v);
+
+    let mut buf = vec![0x6e];
+    buf.extend(core::iter::repeat_n(0x11, DEPTH));
+    buf.push(0x01);
+    let v = Value::decode_with::<SliceInput>(buf.as_slice(), Options::TRUSTED).unwrap();
+    assert_eq!(v.encode_with::<VecOutput>(Options::TRUSTED).unwrap(), buf);
+    let err = Value::decode::<SliceInput>(buf.as_slice()).unwrap_err();
+    assert_eq!((err.err, err.pos), (Error::TooDeep(129), 130));
+
+    // never built, so never dropped or compared
+    const HUGE: usize = 1_000_000;
+    let mut buf = vec![0xc1; HUGE];
+    buf.push(0x0e);
+    let err = Value::decode_with::<SliceInput>(buf.as_slice(), Options::TRUSTED).unwrap_err();
+    assert_eq!((err.err, err.pos), (Error::TooDeep(DEPTH + 1), DEPTH + 1));
+    let err = reader::StreamReader::<SliceInput>::with_options(buf.as_slice(), Options::TRUSTED).find_map(|r| r.err()).unwrap();
+    assert_eq!(err.err, Error::TooDeep(DEPTH + 1));
+    let mut buf = vec![0x6e];
+    buf.extend(core::iter::repeat_n(0x11, HUGE));
+    buf.push(0x01);
+    let err = Value::decode_with::<SliceInput>(buf.as_slice(), Options::TRUSTED).unwrap_err();
+    assert_eq!(err.err, Error::TooDeep(DEPTH + 1));
+}
+
+#[test]
+fn typecheck() {
+    let id = TypeId::Std(StdId { schema: 0x01, id: 0x5f50 });
+    let v: Value<&[u8]> = Value::Map((Type::String, Type::Unknown), seq![
+        (Value::String(s("a")), Value::List(Type::Option(Box::new(Type::U8)), seq![
+            Value::Option(Type::U8, Box::new(Some(Value::U8(1)))),
+            Value::Option(Type::Unknown, Box::new(None)),
+        ])),
+        (Value::String(s("b")), Value::Struct(id, seq![Value::Unit])),
+    ]);
+    assert_eq!(v.typecheck(), Ok(()));
+    let t = Type::Map(Box::new(Type::Unknown), Box::new(Type::List(Box::new(Type::Unknown))));
+    assert_eq!(
+        v.check(&t),
+        Err(TypeError {
+            path: Path(vec![PathSeg::Value(1)]),
+            kind: TypeErrorKind::Mismatch {
+                expected: Type::List(Box::new(Type::Unknown)),
+                found: Type::Struct(id),
+            },
+        }),
+    );
+    assert_eq!(
+        v.check(&Type::Map(Box::new(Type::Bytes), Box::new(Type::Unknown))).unwrap_err().kind,
+        TypeErrorKind::Mismatch {
+            expected: Type::Map(Box::new(Type::Bytes), Box::new(Type::Unknown)),
+            found: Type::Map(Box::new(Type::String), Box::new(Type::Unknown)),
+        },
+    );
+
+    // inner values against declared types
+    let v: Value<&[u8]> = Value::List(Type::U8, seq![
+        Value::U8(1),
+        Value::Option(Type::Unknown, Box::new(Some(Value::Unit))),
+    ]);
+    let kind = TypeErrorKind::Mismatch { expected: Type::U8, found: Type::Option(Box::new(Type::Unknown)) };
+    let err = TypeError { path: Path(vec![PathSeg::Index(SeqKind::List, 1)]), kind };
+    assert_eq!(v.typecheck(), Err(err));
+
+    // declared types refined by the expected ones
+    let v: Value<&[u8]> = Value::Option(Type::Unknown, Box::new(Some(Value::Alias(id, Box::new(Value::Tuple(seq![
+        Value::I8(-1),
+        Value::List(Type::Unknown, seq![Value::Unit]),
+    ]))))));
+    assert_eq!(v.typecheck(), Ok(()));
+    assert_eq!(v.check(&Type::Option(Box::new(Type::Alias(id)))), Ok(()));
+    assert_eq!(
+        v.check(&Type::Option(Box::new(Type::CEnum(id)))).unwrap_err().path,
+        Path(vec![PathSeg::Some]),
+    );
+
+    // opt-in on both sides
+    let buf = expb!("a2 03 0001 0e");
+    let v = Value::decode::<SliceInput>(buf).unwrap();
+    assert_eq!(v, Value::List(Type::U8, seq![Value::U8(1), Value::Unit]));
+    let opts = Options { typecheck: true, ..Options::DEFAULT };
+    let kind = TypeErrorKind::Mismatch { expected: Type::U8, found: Type::Unit };
+    let err = TypeError { path: Path(vec![PathSeg::Index(SeqKind::List, 1)]), kind };
+    assert_eq!(
+        Value::decode_with::<SliceInput>(buf, opts).unwrap_err(),
+        FullError { err: Error::Type(err.clone()), buf, pos: 5, path: Path(vec![PathSeg::Index(SeqKind::List, 1)]) },
+    );
+    assert_eq!(v.encode_with::<VecOutput>(opts), Err(EncodeError::Type(err.clone())));
+    assert_eq!(v.encode::<VecOutput>().unwrap(), buf);
+
+    // streams check each inner value against the types declared by the containers it is in
+    let mut w = writer::StreamWriter::<VecOutput>::with_options(opts);
+    w.begin_list(&Type::U8, 2).unwrap();
+    w.u8(1).unwrap();
+    assert_eq!(w.unit(), Err(EncodeError::Type(err.clone())));
+    // a rejected item is not counted
+    w.u8(2).unwrap();
+    w.end().unwrap();
+    let list = Value::<&[u8]>::List(Type::U8, seq![Value::U8(1), Value::U8(2)]);
+    assert_eq!(w.finish().unwrap(), list.encode::<VecOutput>().unwrap());
+    let mut w = writer::StreamWriter::<VecOutput>::new();
+    w.begin_list(&Type::U8, 2).unwrap();
+    w.u8(1).unwrap();
+    w.unit().unwrap();
+    w.end().unwrap();
+    assert_eq!(w.finish().unwrap(), buf);
+
+    let mut r = reader::StreamReader::<SliceInput>::with_options(buf, opts);
+    assert_eq!(r.next_event(), Ok(Some(Event::ListStart { len: 2, ty: Type::U8 })));
+    assert_eq!(r.next_event(), Ok(Some(Event::Scalar(Value::U8(1)))));
+    assert_eq!(
+        r.next_event(),
+        Err(FullError { err: Error::Type(err), buf, pos: 5, path: Path(vec![PathSeg::Index(SeqKind::List, 1)]) }),
+    );
+    assert_eq!(reader::StreamReader::<SliceInput>::new(buf).count(), 4);
+
+    // containers are checked when started, and narrow the types their inner values are checked against
+    let mismatch = |path, expected, found| EncodeError::Type(TypeError { path: Path(path), kind: TypeErrorKind::Mismatch { expected, found } });
+    let mut w = writer::StreamWriter::<VecOutput>::with_options(opts);
+    w.begin_map(&Type::String, &Type::List(Box::new(Type::U8)), 1).unwrap();
+    w.string("a").unwrap();
+    assert_eq!(
+        w.begin_list(&Type::U16, 0),
+        Err(mismatch(vec![PathSeg::Value(0)], Type::List(Box::new(Type::U8)), Type::List(Box::new(Type::U16)))),
+    );
+    let mut w = writer::StreamWriter::<VecOutput>::with_options(opts);
+    w.begin_list(&Type::List(Box::new(Type::Unknown)), 1).unwrap();
+    w.begin_list(&Type::U8, 1).unwrap();
+    assert_eq!(
+        w.u16(1),
+        Err(mismatch(vec![PathSeg::Index(SeqKind::List, 0), PathSeg::Index(SeqKind::List, 0)], Type::U8, Type::U16)),
+    );
+    let pair = Type::Tuple(seq![Type::U8, Type::String]);
+    let mut w = writer::StreamWriter::<VecOutput>::with_options(opts);
+    w.begin_some(&pair).unwrap();
+    w.begin_tuple(2).unwrap();
+    w.u8(1).unwrap();
+    w.string("a").unwrap();
+    w.end().unwrap();
+    w.end().unwrap();
+    let mut w = writer::StreamWriter::<VecOutput>::with_options(opts);
+    w.begin_some(&pair).unwrap();
+    assert_eq!(
+        w.begin_tuple(3),
+        Err(EncodeError::Type(TypeError { path: Path(vec![PathSeg::Some]), kind: TypeErrorKind::TupleMismatch { expected: pair, len: 3 } })),
+    );
+    // declared tuple lengths are checked against the limits as usual
+    let mut w = writer::StreamWriter::<VecOutput>::with_options(opts);
+    assert_eq!(w.begin_tuple(usize::MAX), Err(EncodeError::TooLongLen(usize::MAX as u64)));
+    let buf = expb!("cd ffff").repeat(Options::DEFAULT.max_depth);
+    let r = reader::StreamReader::<SliceInput>::with_options(buf.as_slice(), opts);
+    let events = r.collect::<Vec<_>>();
+    assert_eq!(events.len(), Options::DEFAULT.max_depth + 1);
+    assert_eq!(events.last().unwrap().as_ref().unwrap_err().err, Error::Read(ReadError::TooShort { rest: 0, expected: 1 }));
+
+    let v = Value::<&[u8]>::List(Type::List(Box::new(Type::U8)), seq![Value::List(Type::U16, Box::new([]))]);
+    let buf = v.encode::<VecOutput>().unwrap();
+    let mut r = reader::StreamReader::<SliceInput>::with_options(buf.as_slice(), opts);
+    assert_eq!(r.next_event(), Ok(Some(Event::ListStart { len: 1, ty: Type::List(Box::new(Type::U8)) })));
+    let err = r.next_event().unwrap_err();
+    assert_eq!(err.path, Path(vec![PathSeg::Index(SeqKind::List, 0)]));
+    assert_eq!(err.err, Error::Type(TypeError {
+        path: err.path.clone(),
+        kind: TypeErrorKind::Mismatch { expected: Type::List(Box::new(Type::U8)), found: Type::List(Box::new(Type::U16)) },
+    }));
+    assert_eq!(v.typecheck(), Err(TypeError { path: err.path, kind: TypeErrorKind::Mismatch {
+        expected: Type::List(Box::new(Type::U8)),
+        found: Type::List(Box::new(Type::U16)),
+    } }));
+}
+
+#[test]
+fn registry() {
+    use alloc::string::ToString;
+    let point = TypeId::Std(StdId { schema: 0x01, id: 0x0001 });
+    let shape = TypeId::Std(StdId { schema: 0x01, id: 0x0002 });
+    let color = TypeId::Std(StdId { schema: 0x01, id: 0x0003 });
+    let name = TypeId::Hash(HashId::from_path("test.Name"));
+    let reg: Registry = [
+        (point, Def::Struct { name: "Point".to_string(), fields: vec![
+            Field { name: "x".to_string(), ty: Type::I32 },
+            Field { name: "y".to_string(), ty: Type::I32 },
+        ] }),
+        (shape, Def::Enum { name: "Shape".to_string(), variants: vec![
+            Variant { id: 0, name: "Dot".to_string(), ty: Type::Struct(point) },
+            Variant { id: 1, name: "Line".to_string(), ty: Type::Tuple(seq![Type::Struct(point), Type::Struct(point)]) },
+        ] }),
+        (color, Def::CEnum { name: "Color".to_string(), variants: vec![
+            CVariant { id: 0, name: "Red".to_string() },
+            CVariant { id: 5, name: "Blue".to_string() },
+        ] }),
+        (name, Def::Alias { name: "Name".to_string(), ty: Type::String }),
+    ].into_iter().collect();
+
+    assert_eq!(reg.len(), 4);
+    assert_eq!(reg.get(&color).unwrap().name(), "Color");
+    assert_eq!(reg.field(&point, 1).unwrap().name, "y");
+    assert_eq!(reg.variant(&shape, 1).unwrap().name, "Line");
+    assert_eq!(reg.c_variant(&color, 5).unwrap().name, "Blue");
+    assert_eq!(reg.alias(&name), Some(&Type::String));
+    assert_eq!(reg.variant(&color, 0), None);
+
+    fn p(x: i32, y: i32) -> Value<&'static [u8]> {
+        Value::Struct(TypeId::Std(StdId { schema: 0x01, id: 0x0001 }), seq![Value::I32(x), Value::I32(y)])
+    }
+    let v = Value::Tuple(seq![
+        Value::Enum(shape, 1, Box::new(Value::Tuple(seq![p(0, 0), p(1, 2)]))),
+        Value::CEnum(color, 5),
+        Value::Alias(name, Box::new(Value::String(s("a")))),
+    ]);
+    assert_eq!(v.typecheck(), Ok(()));
+    assert_eq!(reg.typecheck(&v), Ok(()));
+
+    fn err(path: Vec<PathSeg>, kind: TypeErrorKind) -> CheckResult<()> {
+        Err(TypeError { path: Path(path), kind })
+    }
+    let v = Value::Enum(shape, 1, Box::new(Value::Tuple(seq![p(0, 0), Value::Struct(point, seq![Value::I32(1), Value::I64(2)])])));
+    assert_eq!(v.typecheck(), Ok(()));
+    assert_eq!(
+        reg.typecheck(&v),
+        err(
+            vec![PathSeg::Variant(shape, 1), PathSeg::Index(SeqKind::Tuple, 1), PathSeg::Index(SeqKind::Struct(point), 1)],
+            TypeErrorKind::Mismatch { expected: Type::I32, found: Type::I64 },
+        ),
+    );
+    assert_eq!(
+        reg.typecheck(&Value::<&[u8]>::Enum(shape, 2, Box::new(Value::Unit))),
+        err(vec![], TypeErrorKind::UnknownVariant { id: shape, ev: 2 }),
+    );
+    assert_eq!(
+        reg.check(&Value::<&[u8]>::CEnum(color, 1), &Type::CEnum(color)),
+        err(vec![], TypeErrorKind::UnknownVariant { id: color, ev: 1 }),
+    );
+    assert_eq!(
+        reg.typecheck(&Value::Option(Type::Struct(point), Box::new(Some(Value::<&[u8]>::Struct(point, seq![Value::I32(1)]))))),
+        err(vec![PathSeg::Some], TypeErrorKind::FieldCount { id: point, expected: 2, found: 1 }),
+    );
+    assert_eq!(
+        reg.typecheck(&Value::<&[u8]>::Alias(color, Box::new(Value::Unit))),
+        err(vec![], TypeErrorKind::Mismatch { expected: Type::CEnum(color), found: Type::Alias(color) }),
+    );
+    assert_eq!(
+        reg.typecheck(&Value::Alias(name, Box::new(Value::Bytes(b(b"a"))))),
+        err(vec![PathSeg::Alias(name)], TypeErrorKind::Mismatch { expected: Type::String, found: Type::Bytes }),
+    );
+}
+
+#[test]
+fn derive() {
+    use alloc::{borrow::ToOwned, collections::BTreeMap, string::String};
+    use bcbc_derive::Schema;
+
+    #[derive(Schema, Debug, Clone, Copy, PartialEq)]
+    #[bcbc(std(schema = 0x01, id = 0x0010))]
+    enum Color {
+        Red,
+        Green = 5,
+        Blue,
+    }
+
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    #[bcbc(hash = "bcbc.test.Name")]
+    struct Name(Color);
+
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    #[bcbc(anonymous)]
+    struct Empty;
+
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    #[bcbc(std(schema = 0x01, id = 0x0011))]
+    struct Pair<T>(T, T);
+
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    #[bcbc(std(schema = 0x01, id = 0x0012))]
+    struct Named {
+        color: Color,
+        name: Name,
+        empty: Empty,
+    }
+
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    #[bcbc(hash = "bcbc.test.Shape")]
+    enum Shape {
+        None,
+        Dot(Color),
+        Line(Color, Color),
+        Rect { fill: Color, border: Pair<Color> },
+    }
+
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    #[bcbc(std(schema = 0x01, id = 0x0013))]
+    struct Node {
+        children: Vec<Node>,
+    }
+
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    #[bcbc(std(schema = 0x01, id = 0x0014))]
+    enum List {
+        Nil,
+        Cons(u32, Box<List>),
+    }
+
+    // mutually recursive, the inferred `Vec<Tree>: Schema<__B>` would overflow
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    #[bcbc(std(schema = 0x01, id = 0x0015))]
+    struct Tree {
+        forest: Forest,
+    }
+
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    #[bcbc(std(schema = 0x01, id = 0x0016), bound = "")]
+    struct Forest(Vec<Tree>);
+
+    // bounded by the field types, `String` needs more of `__B` and `BTreeMap` needs `T: Ord`
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    #[bcbc(anonymous)]
+    struct Tagged<T> {
+        items: Vec<(T, String)>,
+    }
+
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    #[bcbc(anonymous)]
+    struct Counts<T>(BTreeMap<T, u8>, u8);
+
+    let color = TypeId::Std(StdId { schema: 0x01, id: 0x0010 });
+    let name = TypeId::Hash(HashId::from_path("bcbc.test.Name"));
+    let pair = TypeId::Std(StdId { schema: 0x01, id: 0x0011 });
+    let named = TypeId::Std(StdId { schema: 0x01, id: 0x0012 });
+    let shape = TypeId::Hash(HashId::from_path("bcbc.test.Shape"));
+    assert_eq!(<Color as crate::Schema<&[u8]>>::ID, color);
+    assert_eq!(<Name as crate::Schema<&[u8]>>::ID, name);
+    assert_eq!(<Empty as crate::Schema<&[u8]>>::ID, TypeId::Anonymous);
+    assert_eq!(<Shape as crate::Schema<&[u8]>>::ID, shape);
+    assert_eq!(<Color as crate::Schema<&[u8]>>::ty(), Type::CEnum(color));
+    assert_eq!(<Name as crate::Schema<&[u8]>>::ty(), Type::Alias(name));
+    assert_eq!(<Pair<u32> as crate::Schema<&[u8]>>::ty(), Type::Struct(pair));
+    assert_eq!(<Shape as crate::Schema<&[u8]>>::ty(), Type::Enum(shape));
+
+    fn case<T: for<'a> crate::Schema<&'a [u8]> + core::fmt::Debug + Clone + PartialEq>(x: T, v: Value<&'static [u8]>) {
+        assert_eq!(x.clone().serialize(), v);
+        let buf = v.encode::<VecOutput>().unwrap();
+        let v2 = Value::decode::<SliceInput>(buf.as_slice()).unwrap();
+        assert_eq!(T::deserialize(v2), Ok(x));
+    }
+
+    case(Color::Red, Value::CEnum(color, 0));
+    case(Color::Green, Value::CEnum(color, 5));
+    case(Color::Blue, Value::CEnum(color, 6));
+    case(Name(Color::Blue), Value::Alias(name, Box::new(Value::CEnum(color, 6))));
+    case(Empty, Value::Struct(TypeId::Anonymous, Box::new([])));
+    case(
+        Named { color: Color::Red, name: Name(Color::Green), empty: Empty },
+        Value::Struct(named, seq![
+            Value::CEnum(color, 0),
+            Value::Alias(name, Box::new(Value::CEnum(color, 5))),
+            Value::Struct(TypeId::Anonymous, Box::new([])),
+        ]),
+    );
+    case(Shape::None, Value::Enum(shape, 0, Box::new(Value::Unit)));
+    case(Shape::Dot(Color::Red), Value::Enum(shape, 1, Box::new(Value::CEnum(color, 0))));
+    case(
+        Shape::Line(Color::Red, Color::Blue),
+        Value::Enum(shape, 2, Box::new(Value::Tuple(seq![Value::CEnum(color, 0), Value::CEnum(color, 6)]))),
+    );
+    case(
+        Shape::Rect { fill: Color::Green, border: Pair(Color::Red, Color::Red) },
+        Value::Enum(shape, 3, Box::new(Value::Struct(TypeId::Anonymous, seq![
+            Value::CEnum(color, 5),
+            Value::Struct(pair, seq![Value::CEnum(color, 0), Value::CEnum(color, 0)]),
+        ]))),
+    );
+
+    let node = TypeId::Std(StdId { schema: 0x01, id: 0x0013 });
+    case(
+        Node { children: vec![Node { children: vec![] }] },
+        Value::Struct(node, seq![Value::List(Type::Struct(node), seq![
+            Value::Struct(node, seq![Value::List(Type::Struct(node), Box::new([]))]),
+        ])]),
+    );
+    let list = TypeId::Std(StdId { schema: 0x01, id: 0x0014 });
+    case(
+        List::Cons(1, Box::new(List::Nil)),
+        Value::Enum(list, 1, Box::new(Value::Tuple(seq![Value::U32(1), Value::Enum(list, 0, Box::new(Value::Unit))]))),
+    );
+    let tree = TypeId::Std(StdId { schema: 0x01, id: 0x0015 });
+    let forest = TypeId::Std(StdId { schema: 0x01, id: 0x0016 });
+    case(
+        Tree { forest: Forest(vec![]) },
+        Value::Struct(tree, seq![Value::Alias(forest, Box::new(Value::List(Type::Struct(tree), Box::new([]))))]),
+    );
+
+    // `String` only in owned storages
+    let tagged = Tagged { items: vec![(1u8, "a".to_owned())] };
+    let v: Value<Vec<u8>> = tagged.clone().serialize();
+    assert_eq!(v, Value::Struct(TypeId::Anonymous, seq![Value::List(Type::Tuple(seq![Type::U8, Type::String]), seq![
+        Value::Tuple(seq![Value::U8(1), Value::String(String::from("a").into())]),
+    ])]));
+    assert_eq!(Tagged::deserialize(v), Ok(tagged));
+    case(
+        Counts([(1u16, 2u8)].into_iter().collect(), 3),
+        Value::Struct(TypeId::Anonymous, seq![Value::Map((Type::U16, Type::U8), seq![(Value::U16(1), Value::U8(2))]), Value::U8(3)]),
+    );
+
+    fn err<T: for<'a> crate::Schema<&'a [u8]> + core::fmt::Debug>(v: Value<&'static [u8]>, path: Vec<PathSeg>, kind: SchemaErrorKind) {
+        assert_eq!(T::deserialize(v).unwrap_err(), SchemaError { path: Path(path), kind });
+    }
+
+    err::<Color>(Value::U8(0), vec![], SchemaErrorKind::Tag { expected: Tag::CEnum, found: Tag::U8 });
+    err::<Color>(Value::CEnum(shape, 0), vec![], SchemaErrorKind::TypeId { expected: color, found: shape });
+    err::<Color>(Value::CEnum(color, 1), vec![], SchemaErrorKind::Variant { id: color, ev: 1 });
+    err::<Name>(
+        Value::Alias(name, Box::new(Value::CEnum(color, 9))),
+        vec![PathSeg::Alias(name)],
+        SchemaErrorKind::Variant { id: color, ev: 9 },
+    );
+    err::<Named>(
+        Value::Struct(named, seq![Value::CEnum(color, 0)]),
+        vec![],
+        SchemaErrorKind::Len { expected: 3, found: 1 },
+    );
+    err::<Shape>(
+        Value::Enum(shape, 3, Box::new(Value::Struct(TypeId::Anonymous, seq![
+            Value::CEnum(color, 5),
+            Value::Struct(pair, seq![Value::CEnum(color, 0), Value::Unit]),
+        ]))),
+        vec![PathSeg::Variant(shape, 3), PathSeg::Index(SeqKind::Struct(TypeId::Anonymous), 1), PathSeg::Index(SeqKind::Struct(pair), 1)],
+        SchemaErrorKind::Tag { expected: Tag::CEnum, found: Tag::Unit },
+    );
+    err::<Shape>(
+        Value::Enum(shape, 0, Box::new(Value::Bool(true))),
+        vec![PathSeg::Variant(shape, 0)],
+        SchemaErrorKind::Tag { expected: Tag::Unit, found: Tag::Bool },
+    );
+    err::<Shape>(
+        Value::Enum(shape, 2, Box::new(Value::Tuple(seq![Value::CEnum(color, 0)]))),
+        vec![PathSeg::Variant(shape, 2)],
+        SchemaErrorKind::Len { expected: 2, found: 1 },
+    );
+}
+
+#[test]
+fn builtin() {
+    use alloc::{collections::BTreeMap, string::String};
+
+    fn case<T: crate::Schema<Vec<u8>> + core::fmt::Debug + Clone + PartialEq>(x: T, t: Type, v: Value<Vec<u8>>) {
+        assert_eq!(T::ty(), t);
+        assert_eq!(x.clone().serialize(), v);
+        assert_eq!(v.check(&t), Ok(()));
+        let buf = v.encode::<VecOutput>().unwrap();
+        let v2 = Value::decode::<SliceInput>(buf.as_slice()).unwrap().map_bytes(<[u8]>::to_vec);
+        assert_eq!(T::deserialize(v2), Ok(x));
+    }
+
+    case(true, Type::Bool, Value::Bool(true));
+    case(7u8, Type::U8, Value::U8(7));
+    case(-7i64, Type::I64, Value::I64(-7));
+    case(u128::MAX, Type::U128, Value::U128(u128::MAX));
+    case('好', Type::Char, Value::Char('好'));
+    case(1.5f64, Type::F64, Value::F64(1.5f64.to_bits()));
+    case((), Type::Unit, Value::Unit);
+    case(String::from("abc"), Type::String, Value::String(String::from("abc").into()));
+    case(vec![1u8, 2], Type::Bytes, Value::Bytes(vec![1, 2]));
+    case([1u8, 2], Type::Bytes, Value::Bytes(vec![1, 2]));
+    case(
+        vec![1u16, 2],
+        Type::List(Box::new(Type::U16)),
+        Value::List(Type::U16, seq![Value::U16(1), Value::U16(2)]),
+    );
+    case(
+        [Some(true), None],
+        Type::List(Box::new(Type::Option(Box::new(Type::Bool)))),
+        Value::List(Type::Option(Box::new(Type::Bool)), seq![
+            Value::Option(Type::Bool, Box::new(Some(Value::Bool(true)))),
+            Value::Option(Type::Bool, Box::new(None)),
+        ]),
+    );
+    case(
+        BTreeMap::from([(1u32, String::from("a"))]),
+        Type::Map(Box::new(Type::U32), Box::new(Type::String)),
+        Value::Map((Type::U32, Type::String), seq![(Value::U32(1), Value::String(String::from("a").into()))]),
+    );
+    case(
+        (1u8, Box::new(2i8), (vec![3u32],)),
+        Type::Tuple(seq![Type::U8, Type::I8, Type::Tuple(seq![Type::List(Box::new(Type::U32))])]),
+        Value::Tuple(seq![Value::U8(1), Value::I8(2), Value::Tuple(seq![Value::List(Type::U32, seq![Value::U32(3)])])]),
+    );
+
+    // borrowed storages can't hold serialized bytes, both forms are accepted
+    assert_eq!(vec![1u8].serialize(), Value::<&[u8]>::List(Type::U8, seq![Value::U8(1)]));
+    assert_eq!(<Vec<u8> as crate::Schema<&[u8]>>::ty(), Type::List(Box::new(Type::U8)));
+    assert_eq!(Schema::<&[u8]>::serialize([1u8]).check(&<[u8; 1] as crate::Schema<&[u8]>>::ty()), Ok(()));
+    assert_eq!(<[u8; 2]>::deserialize(Value::<&[u8]>::Bytes(&[1, 2])), Ok([1, 2]));
+    assert_eq!(<[u8; 1]>::deserialize(Value::<&[u8]>::List(Type::U8, seq![Value::U8(1)])), Ok([1]));
+
+    fn err<T: for<'a> crate::Schema<&'a [u8]> + core::fmt::Debug>(v: Value<&'static [u8]>, path: Vec<PathSeg>, kind: SchemaErrorKind) {
+        assert_eq!(T::deserialize(v).unwrap_err(), SchemaError { path: Path(path), kind });
+    }
+
+    err::<[u8; 3]>(Value::Bytes(&[1, 2]), vec![], SchemaErrorKind::Len { expected: 3, found: 2 });
+    err::<Vec<u8>>(Value::U8(1), vec![], SchemaErrorKind::Tag { expected: Tag::Bytes, found: Tag::U8 });
+    err::<Option<Vec<u16>>>(
+        Value::Option(Type::Unknown, Box::new(Some(Value::List(Type::U16, seq![Value::U16(1), Value::U8(2)])))),
+        vec![PathSeg::Some, PathSeg::Index(SeqKind::List, 1)],
+        SchemaErrorKind::Tag { expected: Tag::U16, found: Tag::U8 },
+    );
+    err::<BTreeMap<u8, bool>>(
+        Value::Map((Type::U8, Type::Bool), seq![(Value::U8(1), Value::Bool(true)), (Value::U8(2), Value::Unit)]),
+        vec![PathSeg::Value(1)],
+        SchemaErrorKind::Tag { expected: Tag::Bool, found: Tag::Unit },
+    );
+    err::<(u8, u8)>(Value::Tuple(seq![Value::U8(1)]), vec![], SchemaErrorKind::Len { expected: 2, found: 1 });
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde() {
+    use alloc::{collections::BTreeMap, string::{String, ToString}};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    enum Kind {
+        A,
+        B(u8),
+        C(u8, bool),
+        D { x: i32 },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Id(u32);
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Doc<'a> {
+        id: Id,
+        name: &'a str,
+        #[serde(with = "serde_bytes_impl")]
+        data: &'a [u8],
+        tags: Vec<String>,
+        kinds: BTreeMap<u8, Kind>,
+        parent: Option<(u64, f64)>,
+    }
+
+    // serde has no built-in impl for borrowed bytes
+    mod serde_bytes_impl {
+        pub fn serialize<S: serde::Serializer>(v: &[u8], s: S) -> Result<S::Ok, S::Error> {
+            s.serialize_bytes(v)
+        }
+
+        pub fn deserialize<'de, D: serde::Deserializer<'de>>(d: D) -> Result<&'de [u8], D::Error> {
+            <&[u8] as serde::Deserialize>::deserialize(d)
+        }
+    }
+
+    let doc = Doc {
+        id: Id(7),
+        name: "doc",
+        data: b"\x01\x02",
+        tags: vec![String::from("x")],
+        kinds: BTreeMap::from([(1, Kind::A), (2, Kind::B(3)), (3, Kind::C(4, true)), (4, Kind::D { x: -1 })]),
+        parent: Some((1, 0.5)),
+    };
+    let st = |s: &str| Value::String(String::from(s).into());
+    let anon = TypeId::Anonymous;
+    let val = Value::<Vec<u8>>::Struct(anon, seq![
+        Value::Alias(anon, Box::new(Value::U32(7))),
+        st("doc"),
+        Value::Bytes(vec![1, 2]),
+        Value::List(Type::String, seq![st("x")]),
+        Value::Map((Type::U8, Type::Unknown), seq![
+            (Value::U8(1), Value::CEnum(anon, 0)),
+            (Value::U8(2), Value::Enum(anon, 1, Box::new(Value::U8(3)))),
+            (Value::U8(3), Value::Enum(anon, 2, Box::new(Value::Tuple(seq![Value::U8(4), Value::Bool(true)])))),
+            (Value::U8(4), Value::Enum(anon, 3, Box::new(Value::Struct(anon, seq![Value::I32(-1)])))),
+        ]),
+        Value::Option(
+            Type::Tuple(seq![Type::U64, Type::F64]),
+            Box::new(Some(Value::Tuple(seq![Value::U64(1), Value::F64(0.5f64.to_bits())]))),
+        ),
+    ]);
+    assert_eq!(crate::to_value(&doc), Ok(val.clone()));
+    let buf = crate::to_vec(&doc).unwrap();
+    assert_eq!(buf, val.encode::<VecOutput>().unwrap());
+    let doc2: Doc = crate::from_slice(&buf).unwrap();
+    assert_eq!(doc2, doc);
+    // borrowed from the input
+    assert!(buf.as_ptr_range().contains(&doc2.name.as_ptr()));
+    assert!(buf.as_ptr_range().contains(&doc2.data.as_ptr()));
+
+    // configured ids
+    let opts = SerdeOptions { ids: crate::hash_ids, ..SerdeOptions::DEFAULT };
+    let id = TypeId::Hash(HashId::from_path("Id"));
+    assert_eq!(crate::to_value_with(&Id(1), opts), Ok(Value::Alias(id, Box::new(Value::U32(1)))));
+    let buf = crate::to_vec_with(&Id(1), opts).unwrap();
+    assert_eq!(crate::from_slice_with::<Id>(&buf, opts), Ok(Id(1)));
+    assert_eq!(
+        crate::from_slice::<Id>(&buf).unwrap_err(),
+        SerdeError { path: Path::new(), kind: SerdeErrorKind::Schema(SchemaErrorKind::TypeId { expected: anon, found: id }) },
+    );
+
+    // values from `Schema` impls
+    assert_eq!(crate::from_value::<Kind>(Value::Enum(anon, 0, Box::new(Value::Unit))), Ok(Kind::A));
+
+    assert_eq!(
+        crate::from_value::<Vec<Option<u8>>>(Value::List(Type::Unknown, seq![
+            Value::Option(Type::U8, Box::new(None)),
+            Value::Option(Type::U8, Box::new(Some(Value::U16(1)))),
+        ])).unwrap_err(),
+        SerdeError {
+            path: Path(vec![PathSeg::Index(SeqKind::List, 1), PathSeg::Some]),
+            kind: SerdeErrorKind::Schema(SchemaErrorKind::Tag { expected: Tag::U8, found: Tag::U16 }),
+        },
+    );
+    assert_eq!(
+        crate::from_value::<Vec<Option<u8>>>(Value::List(Type::Unknown, seq![Value::Option(Type::U8, Box::new(Some(Value::U16(1))))]))
+            .unwrap_err()
+            .to_string(),
+        "Schema(Tag { expected: U8, found: U16 }) at list[0] → some",
+    );
+    assert_eq!(
+        crate::from_value::<Kind>(Value::CEnum(anon, 1)).unwrap_err().kind,
+        SerdeErrorKind::Schema(SchemaErrorKind::Tag { expected: Tag::Enum, found: Tag::CEnum }),
+    );
+    assert!(matches!(
+        crate::from_value::<Kind>(Value::CEnum(anon, 9)).unwrap_err().kind,
+        SerdeErrorKind::Custom(_),
+    ));
+    assert_eq!(
+        crate::from_slice::<u8>(&[]).unwrap_err().kind,
+        SerdeErrorKind::Decode { err: Error::Read(ReadError::TooShort { rest: 0, expected: 1 }), pos: 0 },
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_impls() {
+    use alloc::string::String;
+
+    let id = TypeId::Std(StdId { schema: 0x01, id: 0x0010 });
+    let hash = TypeId::Hash(HashId::from_hash([0, 0x11, 0x22, 0x33, 0x44, 0x55, 0xff]));
+    let val = Value::<Vec<u8>>::Tuple(seq![
+        Value::Unit,
+        Value::F16(0x3c00),
+        Value::String(String::from("abc").into()),
+        Value::Bytes(vec![1, 2]),
+        Value::Option(Type::U8, Box::new(None)),
+        Value::Map((Type::U8, Type::Bool), seq![(Value::U8(1), Value::Bool(true))]),
+        Value::Enum(id, 2, Box::new(Value::CEnum(hash, 3))),
+        Value::Type(Type::List(Box::new(Type::Struct(TypeId::Anonymous)))),
+    ]);
+    let json = concat!(
+        r#"{"Tuple":["Unit",{"F16":15360},{"String":"abc"},{"Bytes":[1,2]},{"Option":["U8",null]},"#,
+        r#"{"Map":[["U8","Bool"],[[{"U8":1},{"Bool":true}]]]},"#,
+        r#"{"Enum":[{"Std":{"schema":1,"id":16}},2,{"CEnum":[{"Hash":"001122334455ff"},3]}]},"#,
+        r#"{"Type":{"List":{"Struct":"Anonymous"}}}]}"#,
+    );
+    assert_eq!(serde_json::to_string(&val).unwrap(), json);
+    assert_eq!(serde_json::from_str::<Value<Vec<u8>>>(json).unwrap(), val);
+
+    // borrowed strings
+    let val = serde_json::from_str::<Value<&[u8]>>(r#"{"String":"abc"}"#).unwrap();
+    assert_eq!(val, Value::String(s("abc")));
+    assert!(serde_json::from_str::<Value<&[u8]>>(r#"{"Bytes":[1]}"#).is_err());
+
+    assert!(serde_json::from_str::<TypeId>(r#"{"Std":{"schema":255,"id":1}}"#).is_err());
+    assert!(serde_json::from_str::<TypeId>(r#"{"Hash":"0011"}"#).is_err());
+
+    // through the non-human-readable serde encoding
+    let buf = crate::to_vec(&hash).unwrap();
+    assert_eq!(crate::from_slice::<TypeId>(&buf), Ok(hash));
+}
+
+#[test]
+fn f16() {
+    use crate::casting::{f16_to_f32, f32_to_f16, f64_to_f16};
+
+    for (f, h) in [
+        (0.0, 0x0000),
+        (-0.0, 0x8000),
+        (1.0, 0x3c00),
+        (-2.0, 0xc000),
+        (65504.0, 0x7bff),
+        (f32::INFINITY, 0x7c00),
+        (6.1035156e-5, 0x0400),
+        (5.9604645e-8, 0x0001),
+    ] {
+        assert_eq!(f32_to_f16(f), h);
+        assert_eq!(f16_to_f32(h), f);
+    }
+    // rounding
+    assert_eq!(f32_to_f16(65520.0), 0x7c00);
+    assert_eq!(f32_to_f16(1.0 + f32::EPSILON), 0x3c00);
+    assert_eq!(f32_to_f16(2.9802322e-8), 0x0000);
+    assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+    // 1 + 2^-11 + 2^-30, just above a tie, which is lost through `f32`
+    let f = 1.0 + 1.0 / 2048.0 + 1.0 / 1073741824.0;
+    assert_eq!(f32_to_f16(f as f32), 0x3c00);
+    assert_eq!(f64_to_f16(f), 0x3c01);
+    // 2^-25, half of the smallest subnormal
+    let f = 1.0 / 33554432.0;
+    assert_eq!(f64_to_f16(f), 0x0000);
+    assert_eq!(f64_to_f16(f * (1.0 + f64::EPSILON)), 0x0001);
+    assert_eq!(f64_to_f16(-1e300), 0xfc00);
+}
+
+#[test]
+fn type_text() {
+    use alloc::string::ToString;
+
+    let std_id = TypeId::Std(StdId { schema: 0x01, id: 0x5f50 });
+    let hash_id = TypeId::Hash(HashId::from_hash([0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54, 0x32]));
+    let boxed = |t| Box::new(t);
+    let cases = [
+        (Type::List(boxed(Type::Map(boxed(Type::U64), boxed(Type::String)))), "list<map<u64, string>>"),
+        (Type::Tuple(Box::new([Type::I64, Type::Unit, Type::Unknown])), "tuple<i64, unit, unknown>"),
+        (Type::Tuple(Box::new([])), "tuple<>"),
+        (Type::Option(boxed(Type::Bytes)), "option<bytes>"),
+        (Type::Struct(std_id), "struct#std:01:5f50"),
+        (Type::Enum(hash_id), "enum#hash:fedcba98765432"),
+        (Type::CEnum(TypeId::Anonymous), "cenum#anon"),
+        (Type::Alias(std_id), "alias#std:01:5f50"),
+        (Type::List(boxed(Type::List(boxed(Type::Struct(TypeId::Anonymous))))), "list<list<struct#anon>>"),
+    ];
+    for (t, s) in cases {
+        assert_eq!(t.to_string(), s);
+        assert_eq!(s.parse::<Type>(), Ok(t));
+    }
+
+    // every tag
+    for n in 0x00..=0x1c {
+        let tag = Tag::try_from(n).unwrap();
+        let t = match tag {
+            Tag::Option => Type::Option(boxed(Type::U8)),
+            Tag::List => Type::List(boxed(Type::Type)),
+            Tag::Map => Type::Map(boxed(Type::TypeId), boxed(Type::Char)),
+            Tag::Tuple => Type::Tuple(Box::new([Type::U128, Type::I128])),
+            Tag::Alias => Type::Alias(hash_id),
+            Tag::CEnum => Type::CEnum(std_id),
+            Tag::Enum => Type::Enum(TypeId::Anonymous),
+            Tag::Struct => Type::Struct(hash_id),
+            tag => tag.name().parse().unwrap(),
+        };
+        assert_eq!(t.as_tag(), tag);
+        assert_eq!(t.to_string().parse::<Type>(), Ok(t));
+    }
+
+    assert_eq!(" map < u8 ,bool> ".parse::<Type>(), Ok(Type::Map(boxed(Type::U8), boxed(Type::Bool))));
+    assert_eq!(Type::parse_prefix("u8>rest"), Ok((Type::U8, ">rest")));
+    let err = |s: &str, pos, expected| assert_eq!(s.parse::<Type>(), Err(ParseTypeError { pos, expected }));
+    err("", 0, "type");
+    err("u7", 0, "type");
+    err("list<u8", 7, "`>`");
+    err("map<u8>", 6, "`,`");
+    err("tuple<u8 u8>", 9, "`,` or `>`");
+    err("u8 u8", 3, "end of input");
+    err("struct", 6, "`#`");
+    err("struct#std:00:0001", 7, "non-reserved schema");
+    err("struct#std:1:0001", 11, "hex digits");
+    err("enum#foo", 5, "type id");
+
+    // nesting is limited like when decoding
+    let opts = Options { max_depth: 2, ..Options::DEFAULT };
+    let t = Type::List(boxed(Type::Tuple(seq![Type::U8])));
+    assert_eq!(Type::parse_with("list<tuple<u8>>", opts), Ok(t));
+    let expected = "type within the depth limit";
+    assert_eq!(Type::parse_with("list<tuple<option<u8>>>", opts), Err(ParseTypeError { pos: 11, expected }));
+    let deep = "list<".repeat(1_000_000);
+    assert_eq!(deep.parse::<Type>(), Err(ParseTypeError { pos: 5 * 128, expected }));
+    assert_eq!(Type::parse_prefix_with(&deep, Options::TRUSTED).unwrap_err().pos, 5 * 1024);
+}
+
+#[test]
+fn type_id_text() {
+    use alloc::string::ToString;
+
+    let std_id = StdId::from_inner(0x01, 0x5f50).unwrap();
+    let hash_id = HashId::from_hash([0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54, 0x32]);
+    for (id, s) in [
+        (TypeId::Anonymous, "anon"),
+        (TypeId::Std(std_id), "std:01:5f50"),
+        (TypeId::Std(StdId::from_inner(0xfe, 0x0001).unwrap()), "std:fe:0001"),
+        (TypeId::Hash(hash_id), "hash:fedcba98765432"),
+    ] {
+        assert_eq!(id.to_string(), s);
+        assert_eq!(s.parse(), Ok(id));
+    }
+    assert_eq!(std_id.to_string(), "std:01:5f50");
+    assert_eq!("std:01:5f50".parse(), Ok(std_id));
+    assert_eq!(hash_id.to_string(), "hash:fedcba98765432");
+    assert_eq!("hash:FEDCBA98765432".parse(), Ok(hash_id));
+
+    fn err<T>(pos: usize, expected: &'static str) -> core::result::Result<T, ParseTypeError> {
+        Err(ParseTypeError { pos, expected })
+    }
+    assert_eq!("std:00:0001".parse::<TypeId>(), err(0, "non-reserved schema"));
+    assert_eq!("std:ff:0001".parse::<StdId>(), err(0, "non-reserved schema"));
+    assert_eq!("std:01:5f5".parse::<StdId>(), err(7, "hex digits"));
+    assert_eq!("std:01-5f50".parse::<StdId>(), err(6, "`:`"));
+    assert_eq!("hash:fedcba9876543".parse::<HashId>(), err(5, "hex digits"));
+    assert_eq!("hash:fedcba9876543210".parse::<HashId>(), err(19, "end of input"));
+    assert_eq!("anon".parse::<StdId>(), err(0, "`std:`"));
+    assert_eq!("std:01:5f50".parse::<HashId>(), err(0, "`hash:`"));
+    assert_eq!("anonymous".parse::<TypeId>(), err(4, "end of input"));
+    assert_eq!("".parse::<TypeId>(), err(0, "type id"));
+}
+
+#[test]
+fn explain() {
+    use alloc::string::ToString;
+
+    let v: Value<&[u8]> = Value::Map((Type::U64, Type::String), seq![
+        (Value::U64(1), Value::String(s("ab"))),
+        (Value::U64(300), Value::String(s(""))),
+    ]);
+    let buf = v.encode::<VecOutput>().unwrap();
+    let ex = Value::explain::<SliceInput>(buf.as_slice());
+    assert_eq!(ex.value, Some(v));
+    assert_eq!(ex.to_string(), "\
+0000..0001  b2 → H4::Map len=2
+0001..0002    06 → Tag::U64
+0002..0003    0e → Tag::String
+0003..0004    03 → H4::N1 L4::U64
+0004..0005      01 → 1u64
+0005..0006    82 → H4::String len=2
+0006..0008      61 62 → \"ab\"
+0008..0009    13 → H4::N2 L4::U64
+0009..000b      01 2c → 300u64
+000b..000c    80 → H4::String len=0
+");
+    assert_eq!(Value::explain::<SliceInput>(expb!("06")).to_string(), "\
+0000..0001  06 → H4::N1 L4::P32
+0001..0001  → error: read error: unexpected end of input: 1 bytes needed, 0 left
+");
+
+    fn notes(buf: &[u8]) -> Vec<(core::ops::Range<usize>, NoteKind)> {
+        let ex = Value::explain::<SliceInput>(buf);
+        assert_eq!(ex.value, None);
+        ex.notes.into_iter().map(|n| (n.range, n.kind)).collect()
+    }
+
+    assert_eq!(notes(expb!("8c 00")), vec![
+        (0..1, NoteKind::Header(H4::String, EXT8)),
+        (1..2, NoteKind::Error(Error::ExtvarTooLong { l4: EXT8, exp_l4: 0u8.try_into().unwrap(), u: 0 })),
+    ]);
+    assert_eq!(notes(expb!("11 0001")), vec![
+        (0..1, NoteKind::Header(H4::N2, L4::U16)),
+        (1..3, NoteKind::Error(Error::BytevarLongerThanExpected { len: 2, nlen: 2, exp_len: 1, buf: hex!("00 00 00 00 00 00 00 01") })),
+    ]);
+    assert_eq!(notes(expb!("c2 0e 3f")), vec![
+        (0..1, NoteKind::Header(H4::Tuple, L4::U32)),
+        (1..2, NoteKind::Header(H4::N1, L4::EXT1)),
+        (2..3, NoteKind::Header(H4::N4, L4::EXT2)),
+        (3..3, NoteKind::Error(Error::Ext2(0x3))),
+    ]);
+    assert_eq!(notes(expb!("a1 13 02 03 0e 03")), vec![
+        (0..1, NoteKind::Header(H4::List, L4::U16)),
+        (1..2, NoteKind::Tag(Tag::Tuple)),
+        (2..3, NoteKind::TupleSize(2)),
+        (3..4, NoteKind::Tag(Tag::U8)),
+        (4..5, NoteKind::Tag(Tag::String)),
+        (5..6, NoteKind::Header(H4::N1, L4::U64)),
+        (6..6, NoteKind::Error(Error::Read(ReadError::TooShort { rest: 0, expected: 1 }))),
+    ]);
+    // trailing bytes
+    assert_eq!(notes(expb!("0e 00 00")), vec![
+        (0..1, NoteKind::Header(H4::N1, L4::EXT1)),
+        (1..3, NoteKind::Error(Error::Read(ReadError::TooLong { rest: 2 }))),
+    ]);
+}
+
+#[test]
+fn spans() {
+    let id = TypeId::Std(StdId { schema: 0x01, id: 0x0002 });
+    let v: Value<&[u8]> = Value::Struct(id, seq![
+        Value::List(Type::Alias(id), seq![Value::Alias(id, Box::new(Value::U8(1)))]),
+        Value::Map((Type::U64, Type::String), seq![(Value::U64(1), Value::String(s("ab")))]),
+        Value::String(s("abcdefghijklmnopqrst")),
+    ]);
+    let buf = v.encode::<VecOutput>().unwrap();
+    let (v2, span) = Value::decode_spanned::<SliceInput>(buf.as_slice()).unwrap();
+    assert_eq!(v2, v);
+
+    let leaf = |range: core::ops::Range<usize>, header| Span { range, header, ty: None, type_id: None, inner: vec![] };
+    assert_eq!(span, Span {
+        range: 0..45,
+        header: 0..1,
+        ty: None,
+        type_id: Some(1..4),
+        inner: vec![
+            Span {
+                range: 4..15,
+                header: 4..5,
+                // Tag::Alias with its TypeId
+                ty: Some(5..9),
+                type_id: None,
+                inner: vec![Span { range: 9..15, header: 9..10, ty: None, type_id: Some(10..13), inner: vec![leaf(13..15, 13..14)] }],
+            },
+            Span {
+                range: 15..23,
+                header: 15..16,
+                ty: Some(16..18),
+                type_id: None,
+                inner: vec![leaf(18..20, 18..19), leaf(20..23, 20..21)],
+            },
+            // with an ext8 length
+            leaf(23..45, 23..25),
+        ],
+    });
+    assert_eq!(&buf[span.inner[1].inner[1].range.clone()], expb!("82 6162"));
+    let at = |segs: &[PathSeg]| span.range_at(&Path(segs.to_vec()));
+    assert_eq!(at(&[]), Some(0..45));
+    assert_eq!(at(&[PathSeg::Index(SeqKind::Struct(id), 0), PathSeg::Index(SeqKind::List, 0), PathSeg::Alias(id)]), Some(13..15));
+    assert_eq!(at(&[PathSeg::Index(SeqKind::Struct(id), 1), PathSeg::Value(0)]), Some(20..23));
+    assert_eq!(at(&[PathSeg::Index(SeqKind::Struct(id), 1), PathSeg::Type]), Some(16..18));
+    assert_eq!(at(&[PathSeg::Index(SeqKind::Struct(id), 1), PathSeg::Key(1)]), None);
+    assert!(Value::decode_spanned::<SliceInput>(expb!("0e 00")).is_err());
+}
+
+#[test]
+fn error_path() {
+    use alloc::string::ToString;
+
+    let id = TypeId::Std(StdId { schema: 0x01, id: 0x5f49 });
+    let v: Value<&[u8]> = Value::Tuple(seq![
+        Value::Unit,
+        Value::Map((Type::U8, Type::List(Box::new(Type::Enum(id)))), seq![(
+            Value::U8(1),
+            Value::List(Type::Enum(id), seq![Value::Enum(id, 5, Box::new(Value::Option(Type::U8, Box::new(None))))]),
+        )]),
+    ]);
+    let mut buf = v.encode::<VecOutput>().unwrap();
+    // the type annotation of `None`
+    *buf.last_mut().unwrap() = 0x1f;
+    let path = Path(vec![
+        PathSeg::Index(SeqKind::Tuple, 1),
+        PathSeg::Value(0),
+        PathSeg::Index(SeqKind::List, 0),
+        PathSeg::Variant(id, 5),
+        PathSeg::Type,
+    ]);
+    let err = Value::decode::<SliceInput>(buf.as_slice()).unwrap_err();
+    assert_eq!(err.err, Error::Tag(0x1f));
+    assert_eq!(err.path, path);
+    assert_eq!(path.to_string(), "tuple[1] → map[0].value → list[0] → enum(std:01:5f49)[5].payload → type");
+    assert_eq!(Path::new().to_string(), "root");
+    let err2 = reader::StreamReader::<SliceInput>::new(buf.as_slice()).find_map(|r| r.err()).unwrap();
+    assert_eq!(err2, err);
+
+    // map keys, and trailing bytes at the root
+    let buf = expb!("b1 03 03 1f 00");
+    assert_eq!(Value::decode::<SliceInput>(buf).unwrap_err().path, Path(vec![PathSeg::Key(0)]));
+    assert_eq!(reader::StreamReader::<SliceInput>::new(buf).find_map(|r| r.err()).unwrap().path, Path(vec![PathSeg::Key(0)]));
+    let buf = expb!("0e 00");
+    assert_eq!(Value::decode::<SliceInput>(buf).unwrap_err().path, Path::new());
+    assert_eq!(reader::StreamReader::<SliceInput>::new(buf).find_map(|r| r.err()).unwrap().path, Path::new());
+}
+
+#[test]
+fn error_display() {
+    use alloc::string::ToString;
+
+    let err = |buf: &'static [u8]| Value::decode::<SliceInput>(buf).unwrap_err().to_string();
+    assert_eq!(
+        err(expb!("11 0001")),
+        "bytevar 00 00 00 00 00 00 00 01 of 2 bytes, longer than the needed 1 at byte 3 in root, near 11 00 01 |",
+    );
+    assert_eq!(err(expb!("8c 00")), "extvar 0 in 1 byte where the header suffices at byte 2 in root, near 8c 00 |");
+    assert_eq!(
+        err(expb!("c2 0e a1 1f 00 01 02 03 04 05 06 07 08 09")),
+        "invalid type tag 0x1f at byte 4 in tuple[1] → type, near c2 0e a1 1f | 00 01 02 03 04 05 06 07 ..",
+    );
+    assert_eq!(err(expb!("0e 00 00")), "read error: 2 trailing bytes at byte 1 in root, near 0e | 00 00");
+    assert_eq!(Error::Char(0xd800).to_string(), "invalid char 0xd800");
+    assert_eq!(
+        TypeError { path: Path(vec![PathSeg::Some]), kind: TypeErrorKind::Mismatch { expected: Type::U8, found: Type::Unit } }.to_string(),
+        "expected u8, found unit at some",
+    );
+    assert_eq!(
+        Value::decode_lenient::<SliceInput>(expb!("a2 0e 0f 0000d800")).diags[0].to_string(),
+        "skipped: invalid char 0xd800 at byte 7 in list[0]",
+    );
+    assert_eq!(
+        SchemaError { path: Path(vec![PathSeg::Some]), kind: SchemaErrorKind::Tag { expected: Tag::U8, found: Tag::String } }.to_string(),
+        "expected u8, found string at some",
+    );
+
+    #[cfg(feature = "std")]
+    {
+        use std::error::Error as _;
+
+        let full = Value::decode::<SliceInput>(expb!("82 ffff")).unwrap_err();
+        let source = full.source().unwrap();
+        assert_eq!(source.to_string(), alloc::format!("invalid UTF-8 string: {}", source.source().unwrap()));
+        assert_eq!(source.source().unwrap().to_string(), core::str::from_utf8(expb!("ffff")).unwrap_err().to_string());
+        let full = Value::decode::<SliceInput>(expb!("01")).unwrap_err();
+        assert_eq!(full.source().unwrap().source().unwrap().to_string(), "unexpected end of input: 1 bytes needed, 0 left");
+        assert!(Error::Tag(0x1f).source().is_none());
+
+        // borrowing the input, so not `'static`
+        fn boxed(buf: &[u8]) -> std::result::Result<Value<&[u8]>, std::boxed::Box<dyn std::error::Error>> {
+            Ok(Value::decode::<SliceInput>(buf).map_err(FullError::into_owned)?)
+        }
+        let err = boxed(expb!("0e 00 00")).unwrap_err();
+        assert_eq!(err.to_string(), "read error: 2 trailing bytes at byte 1 in root, near 0e | 00 00");
+        let _: std::boxed::Box<dyn std::error::Error> = Box::new("u9".parse::<Type>().unwrap_err());
+    }
+}
+
+#[test]
+fn lenient() {
+    fn diag(severity: Severity, err: Error, pos: usize, path: Vec<PathSeg>) -> Diagnostic {
+        Diagnostic { severity, err, pos, path: Path(path) }
+    }
+    let dec = |buf: &'static [u8]| Value::decode_lenient::<SliceInput>(buf);
+
+    // non-canonical, rejected by default
+    let buf = expb!("21 000001");
+    assert!(Value::decode::<SliceInput>(buf).is_err());
+    assert_eq!(dec(buf), Recovered {
+        value: Some(Value::U16(1)),
+        diags: vec![diag(Severity::Warning, Error::BytevarLongerThanType { len: 3, nlen: 2, buf: hex!("00 00 00 00 00 00 00 01") }, 4, vec![])],
+    });
+    assert_eq!(dec(expb!("a1 04 11 0001")), Recovered {
+        value: Some(Value::List(Type::U16, seq![Value::U16(1)])),
+        diags: vec![diag(
+            Severity::Warning,
+            Error::BytevarLongerThanExpected { len: 2, nlen: 2, exp_len: 1, buf: hex!("00 00 00 00 00 00 00 01") },
+            5,
+            vec![PathSeg::Index(SeqKind::List, 0)],
+        )],
+    });
+    assert_eq!(dec(expb!("8c 00")), Recovered {
+        value: Some(Value::String(s(""))),
+        diags: vec![diag(Severity::Warning, Error::ExtvarTooLong { l4: EXT8, exp_l4: 0u8.try_into().unwrap(), u: 0 }, 2, vec![])],
+    });
+    // the rest of the list is read
+    assert_eq!(dec(expb!("a3 0e 81 61 82 ffff 81 62")), Recovered {
+        value: Some(Value::List(Type::String, seq![Value::String(s("a")), Value::String(s("b"))])),
+        diags: vec![diag(Severity::Skipped, Error::Utf8(core::str::from_utf8(expb!("ffff")).unwrap_err()), 7, vec![PathSeg::Index(SeqKind::List, 1)])],
+    });
+    // the whole item is dropped
+    assert_eq!(dec(expb!("a2 13 02 03 1a c2 00 01 0f 0000d800 c2 00 02 0f 00000063")), Recovered {
+        value: Some(Value::List(Type::Tuple(seq![Type::U8, Type::Char]), seq![Value::Tuple(seq![Value::U8(2), Value::Char('c')])])),
+        diags: vec![diag(Severity::Skipped, Error::Char(0xd800), 13, vec![PathSeg::Index(SeqKind::List, 0), PathSeg::Index(SeqKind::Tuple, 1)])],
+    });
+    // stops where lengths do not allow, with what was read
+    assert_eq!(dec(expb!("c3 0e 00 05 3f")), Recovered {
+        value: Some(Value::Tuple(seq![Value::Unit, Value::U8(5)])),
+        diags: vec![diag(Severity::Error, Error::Ext2(0x3), 5, vec![PathSeg::Index(SeqKind::Tuple, 2)])],
+    });
+    assert_eq!(dec(expb!("a3 0e 81 61 82 ffff 81")), Recovered {
+        value: Some(Value::List(Type::String, seq![Value::String(s("a"))])),
+        diags: vec![
+            diag(Severity::Skipped, Error::Utf8(core::str::from_utf8(expb!("ffff")).unwrap_err()), 7, vec![PathSeg::Index(SeqKind::List, 1)]),
+            diag(Severity::Error, Error::Read(ReadError::TooShort { rest: 0, expected: 1 }), 8, vec![PathSeg::Index(SeqKind::List, 1)]),
+        ],
+    });
+    assert_eq!(dec(expb!("82 ffff")).value, None);
+    // in the type annotation of an item
+    assert_eq!(dec(expb!("c1 a1 ff")), Recovered {
+        value: Some(Value::Tuple(Box::new([]))),
+        diags: vec![diag(Severity::Error, Error::Tag(0xff), 3, vec![PathSeg::Index(SeqKind::Tuple, 0), PathSeg::Type])],
+    });
+    assert_eq!(dec(expb!("0e 00")), Recovered {
+        value: Some(Value::Unit),
+        diags: vec![diag(Severity::Error, Error::Read(ReadError::TooLong { rest: 1 }), 1, vec![])],
+    });
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json() {
+    use alloc::string::String;
+    use serde_json::json;
+
+    let st = |s: &str| Value::String(String::from(s).into());
+    let color = TypeId::Std(StdId { schema: 0x01, id: 0x0010 });
+    let point = TypeId::Std(StdId { schema: 0x01, id: 0x0011 });
+    let shape = TypeId::Hash(HashId::from_hash([0, 1, 2, 3, 4, 5, 6]));
+
+    let val = Value::<Vec<u8>>::Tuple(seq![
+        Value::Unit,
+        Value::Option(Type::Unit, Box::new(Some(Value::Unit))),
+        Value::Option(Type::Unit, Box::new(None)),
+        Value::U128(u128::MAX),
+        Value::F32(1.5f32.to_bits()),
+        Value::Bytes(vec![0xab, 1]),
+        Value::Map((Type::String, Type::Char), seq![(st("a"), Value::Char('b'))]),
+        Value::Enum(shape, 1, Box::new(Value::Struct(point, seq![Value::I8(-1), Value::I8(2)]))),
+        Value::CEnum(color, 2),
+        Value::TypeId(TypeId::Anonymous),
+    ]);
+    let j = json!([
+        null,
+        { "Option": ["Unit", null] },
+        { "Option": ["Unit"] },
+        { "U128": "340282366920938463463374607431768211455" },
+        { "F32": 1069547520 },
+        { "Bytes": "ab01" },
+        { "Map": [["String", "Char"], [["a", { "Char": "b" }]]] },
+        { "Enum": [{ "Hash": "00010203040506" }, 1, { "Struct": [{ "Std": { "schema": 1, "id": 17 } }, [{ "I8": -1 }, { "I8": 2 }]] }] },
+        { "CEnum": [{ "Std": { "schema": 1, "id": 16 } }, 2] },
+        { "TypeId": "Anonymous" },
+    ]);
+    assert_eq!(val.to_json(), j);
+    assert_eq!(Value::from_json(&j), Ok(val));
+
+    let reg: Registry = [
+        (color, Def::CEnum { name: "Color".into(), variants: vec![
+            CVariant { id: 1, name: "Red".into() },
+            CVariant { id: 2, name: "Green".into() },
+        ] }),
+        (point, Def::Struct { name: "Point".into(), fields: vec![
+            Field { name: "x".into(), ty: Type::I8 },
+            Field { name: "y".into(), ty: Type::I8 },
+            Field { name: "label".into(), ty: Type::Option(Box::new(Type::String)) },
+        ] }),
+        (shape, Def::Enum { name: "Shape".into(), variants: vec![
+            Variant { id: 0, name: "Empty".into(), ty: Type::Unit },
+            Variant { id: 1, name: "Dot".into(), ty: Type::Struct(point) },
+            Variant { id: 2, name: "Fill".into(), ty: Type::CEnum(color) },
+        ] }),
+    ].into_iter().collect();
+    let t = Type::List(Box::new(Type::Enum(shape)));
+    let val = Value::<Vec<u8>>::List(Type::Enum(shape), seq![
+        Value::Enum(shape, 0, Box::new(Value::Unit)),
+        Value::Enum(shape, 1, Box::new(Value::Struct(point, seq![
+            Value::I8(-1),
+            Value::I8(2),
+            Value::Option(Type::String, Box::new(Some(st("p")))),
+        ]))),
+        Value::Enum(shape, 2, Box::new(Value::CEnum(color, 2))),
+    ]);
+    let j = json!(["Empty", { "Dot": { "x": -1, "y": 2, "label": "p" } }, { "Fill": "Green" }]);
+    assert_eq!(val.to_json_with(&reg), j);
+    assert_eq!(Value::from_json_with(&j, &t, &reg), Ok(val));
+
+    let floats = Value::<Vec<u8>>::Tuple(seq![
+        Value::F16(0x3c00),
+        Value::F32(0.1f32.to_bits()),
+        Value::F64(f64::NAN.to_bits()),
+        Value::U128(1),
+        Value::Bytes(vec![1]),
+    ]);
+    let t = Type::Tuple(seq![Type::F16, Type::F32, Type::F64, Type::U128, Type::Bytes]);
+    let j = json!([1.0, 0.1, "NaN", 1, "01"]);
+    assert_eq!(floats.to_json_with(&reg), j);
+    let back = Value::from_json_with(&j, &t, &reg).unwrap();
+    assert_eq!(back.to_json_with(&reg), j);
+
+    // left out optional fields
+    let j = json!({ "Dot": { "x": 0, "y": 0 } });
+    assert_eq!(
+        Value::from_json_with(&j, &Type::Enum(shape), &reg),
+        Ok(Value::Enum(shape, 1, Box::new(Value::Struct(point, seq![
+            Value::I8(0),
+            Value::I8(0),
+            Value::Option(Type::String, Box::new(None)),
+        ])))),
+    );
+
+    fn err(path: Vec<PathSeg>, kind: JsonErrorKind) -> JsonResult<Value<Vec<u8>>> {
+        Err(JsonError { path: Path(path), kind })
+    }
+
+    assert_eq!(
+        Value::from_json_with(&json!([{ "Dot": { "x": 300, "y": 0 } }]), &Type::List(Box::new(Type::Enum(shape))), &reg),
+        err(vec![PathSeg::Index(SeqKind::List, 0), PathSeg::Variant(shape, 1), PathSeg::Index(SeqKind::Struct(point), 0)], JsonErrorKind::OutOfRange),
+    );
+    assert_eq!(
+        Value::from_json_with(&json!({ "Dot": { "x": 0 } }), &Type::Enum(shape), &reg),
+        err(vec![PathSeg::Variant(shape, 1)], JsonErrorKind::MissingField("y".into())),
+    );
+    assert_eq!(
+        Value::from_json_with(&json!({ "Fill": "Blue" }), &Type::Enum(shape), &reg),
+        err(vec![PathSeg::Variant(shape, 2)], JsonErrorKind::Variant("Blue".into())),
+    );
+    assert_eq!(
+        Value::from_json_with(&json!(1), &Type::Alias(color), &reg),
+        err(vec![], JsonErrorKind::Type(Type::Alias(color))),
+    );
+    assert_eq!(
+        Value::from_json(&json!({ "List": ["U8", [{ "U8": 1 }, { "U9": 1 }]] })),
+        err(vec![PathSeg::Index(SeqKind::List, 1)], JsonErrorKind::Tag("U9".into())),
+    );
+    assert_eq!(Value::from_json(&json!({ "Bytes": "abc" })), err(vec![], JsonErrorKind::Hex));
 }
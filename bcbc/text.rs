@@ -0,0 +1,454 @@
+// text notation of `Type`, e.g. `list<map<u64, string>>`, `tuple<i64, unit, unknown>`, `struct#std:01:5f50`
+// and `enum#hash:fedcba98765432`, with `TypeId`s written as `anon`, `std:<schema>:<id>` or `hash:<hash>` in
+// fixed-width lowercase hex.
+//
+// `Value`s are printed in a literal notation (parsed by `bcs-parser`) that round-trips exactly:
+// - `unit`, `true`, `false`, integers with their type as suffix, e.g. `123u64` and `-7i64`
+// - floats as the shortest decimal of their bits, e.g. `1.5f32`, and as bits when NaN or infinite, e.g.
+//   `f32(0x7fc00000)`, with `F16`s through `f32`
+// - `'c'`, `"str"` with Rust escapes, and `b"bytes"` with `\xNN` escapes for other than printable ASCII
+// - `some<bool>(true)`, `none<string>`, `list<string>["a"]`, `map<u64, string>{1u64: "a"}`, `tuple(1u8, unit)`
+// - `alias#id(v)`, `cenum#id(ev)`, `enum#id(ev, v)`, `struct#id(v, ..)`, `type(list<u8>)` and
+//   `typeid(std:01:5f50)`
+// With `{:#}`, non-empty sequences are printed one item per line.
+
+use core::{fmt, str::FromStr};
+use super::*;
+
+const NAMES: [(Tag, &str); 29] = [
+    (Tag::Unknown, "unknown"),
+    (Tag::Unit, "unit"),
+    (Tag::Bool, "bool"),
+    (Tag::U8, "u8"),
+    (Tag::U16, "u16"),
+    (Tag::U32, "u32"),
+    (Tag::U64, "u64"),
+    (Tag::I8, "i8"),
+    (Tag::I16, "i16"),
+    (Tag::I32, "i32"),
+    (Tag::I64, "i64"),
+    (Tag::F16, "f16"),
+    (Tag::F32, "f32"),
+    (Tag::F64, "f64"),
+    (Tag::String, "string"),
+    (Tag::Bytes, "bytes"),
+    (Tag::Option, "option"),
+    (Tag::List, "list"),
+    (Tag::Map, "map"),
+    (Tag::Tuple, "tuple"),
+    (Tag::Alias, "alias"),
+    (Tag::CEnum, "cenum"),
+    (Tag::Enum, "enum"),
+    (Tag::Struct, "struct"),
+    (Tag::Type, "type"),
+    (Tag::TypeId, "typeid"),
+    (Tag::Char, "char"),
+    (Tag::U128, "u128"),
+    (Tag::I128, "i128"),
+];
+
+impl Tag {
+    pub fn name(&self) -> &'static str {
+        NAMES.iter().find(|(tag, _)| tag == self).unwrap().1
+    }
+
+    pub fn from_name(name: &str) -> Option<Tag> {
+        NAMES.iter().find(|(_, n)| *n == name).map(|(tag, _)| *tag)
+    }
+}
+
+impl fmt::Display for StdId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "std:{:02x}:{:04x}", self.schema, self.id)
+    }
+}
+
+impl fmt::Display for HashId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("hash:")?;
+        for b in self.hash {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TypeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeId::Anonymous => f.write_str("anon"),
+            TypeId::Std(id) => write!(f, "{}", id),
+            TypeId::Hash(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_tag().name())?;
+        match self {
+            Type::Option(t) | Type::List(t) => write!(f, "<{}>", t),
+            Type::Map(k, v) => write!(f, "<{}, {}>", k, v),
+            Type::Tuple(ts) => {
+                f.write_str("<")?;
+                for (i, t) in ts.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", t)?;
+                }
+                f.write_str(">")
+            }
+            Type::Alias(id) | Type::CEnum(id) | Type::Enum(id) | Type::Struct(id) => write!(f, "#{}", id),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for ParseTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {} at {}", self.expected, self.pos)
+    }
+}
+
+struct TextReader<'a> {
+    s: &'a str,
+    pos: usize,
+    // of nested container types, limited by `Options::max_depth`
+    depth: usize,
+    max_depth: usize,
+}
+
+impl<'a> TextReader<'a> {
+    fn new(s: &'a str, opts: Options) -> TextReader<'a> {
+        TextReader { s, pos: 0, depth: 0, max_depth: opts.max_depth }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    fn err<T>(&self, expected: &'static str) -> ParseTypeResult<T> {
+        Err(ParseTypeError { pos: self.pos, expected })
+    }
+
+    fn skip_ws(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_ws();
+        let eaten = self.rest().starts_with(c);
+        if eaten {
+            self.pos += c.len_utf8();
+        }
+        eaten
+    }
+
+    fn expect(&mut self, c: char, expected: &'static str) -> ParseTypeResult<()> {
+        if self.eat(c) { Ok(()) } else { self.err(expected) }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !f(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn hex<const N: usize>(&mut self) -> ParseTypeResult<[u8; N]> {
+        let digits = self.rest().as_bytes();
+        let mut bytes = [0; N];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            let digit = |j: usize| digits.get(i * 2 + j).and_then(|&c| (c as char).to_digit(16));
+            let (Some(h), Some(l)) = (digit(0), digit(1)) else {
+                return self.err("hex digits");
+            };
+            *b = (h << 4 | l) as u8;
+        }
+        self.pos += N * 2;
+        Ok(bytes)
+    }
+
+    fn std_id(&mut self) -> ParseTypeResult<StdId> {
+        let start = self.pos;
+        if !self.rest().starts_with("std:") {
+            return self.err("`std:`");
+        }
+        self.pos += 4;
+        let [schema] = self.hex::<1>()?;
+        self.expect(':', "`:`")?;
+        let id = u16::from_be_bytes(self.hex::<2>()?);
+        StdId::from_inner(schema, id).ok_or(ParseTypeError { pos: start, expected: "non-reserved schema" })
+    }
+
+    fn hash_id(&mut self) -> ParseTypeResult<HashId> {
+        if !self.rest().starts_with("hash:") {
+            return self.err("`hash:`");
+        }
+        self.pos += 5;
+        Ok(HashId::from_hash(self.hex::<7>()?))
+    }
+
+    fn type_id(&mut self) -> ParseTypeResult<TypeId> {
+        let rest = self.rest();
+        if rest.starts_with("std:") {
+            self.std_id().map(TypeId::Std)
+        } else if rest.starts_with("hash:") {
+            self.hash_id().map(TypeId::Hash)
+        } else if rest.starts_with("anon") {
+            self.pos += 4;
+            Ok(TypeId::Anonymous)
+        } else {
+            self.err("type id")
+        }
+    }
+
+    // all of the input
+    fn finish<T>(mut self, t: T) -> ParseTypeResult<T> {
+        self.skip_ws();
+        if !self.rest().is_empty() {
+            return self.err("end of input");
+        }
+        Ok(t)
+    }
+
+    fn ty(&mut self) -> ParseTypeResult<Type> {
+        self.skip_ws();
+        let start = self.pos;
+        let Some(tag) = Tag::from_name(self.take_while(|c| c.is_ascii_alphanumeric())) else {
+            self.pos = start;
+            return self.err("type");
+        };
+        let boxed = |t| Box::new(t);
+        Ok(match tag {
+            Tag::Option | Tag::List | Tag::Map | Tag::Tuple => {
+                if self.depth >= self.max_depth {
+                    self.pos = start;
+                    return self.err("type within the depth limit");
+                }
+                self.depth += 1;
+                self.expect('<', "`<`")?;
+                let t = match tag {
+                    Tag::Option => Type::Option(boxed(self.ty()?)),
+                    Tag::List => Type::List(boxed(self.ty()?)),
+                    Tag::Map => {
+                        let k = self.ty()?;
+                        self.expect(',', "`,`")?;
+                        Type::Map(boxed(k), boxed(self.ty()?))
+                    }
+                    _ => {
+                        let mut ts = Vec::new();
+                        while !self.eat('>') {
+                            if !ts.is_empty() {
+                                self.expect(',', "`,` or `>`")?;
+                            }
+                            ts.push(self.ty()?);
+                        }
+                        self.depth -= 1;
+                        return Ok(Type::Tuple(ts.into_boxed_slice()));
+                    }
+                };
+                self.expect('>', "`>`")?;
+                self.depth -= 1;
+                t
+            }
+            Tag::Alias | Tag::CEnum | Tag::Enum | Tag::Struct => {
+                self.expect('#', "`#`")?;
+                self.skip_ws();
+                let id = self.type_id()?;
+                match tag {
+                    Tag::Alias => Type::Alias(id),
+                    Tag::CEnum => Type::CEnum(id),
+                    Tag::Enum => Type::Enum(id),
+                    _ => Type::Struct(id),
+                }
+            }
+            // the others are handled above
+            scalar => Type::from_scalar_tag(scalar).unwrap(),
+        })
+    }
+}
+
+impl Type {
+    // parses a type at the start of `s`, returning the rest, for embedding in other notations
+    pub fn parse_prefix(s: &str) -> ParseTypeResult<(Type, &str)> {
+        Type::parse_prefix_with(s, Options::DEFAULT)
+    }
+
+    pub fn parse_prefix_with(s: &str, opts: Options) -> ParseTypeResult<(Type, &str)> {
+        let mut r = TextReader::new(s, opts);
+        let t = r.ty()?;
+        Ok((t, r.rest()))
+    }
+
+    // same as `FromStr`, which uses `Options::DEFAULT`
+    pub fn parse_with(s: &str, opts: Options) -> ParseTypeResult<Type> {
+        let mut r = TextReader::new(s, opts);
+        let t = r.ty()?;
+        r.finish(t)
+    }
+}
+
+impl FromStr for Type {
+    type Err = ParseTypeError;
+
+    fn from_str(s: &str) -> ParseTypeResult<Type> {
+        Type::parse_with(s, Options::DEFAULT)
+    }
+}
+
+impl FromStr for TypeId {
+    type Err = ParseTypeError;
+
+    fn from_str(s: &str) -> ParseTypeResult<TypeId> {
+        let mut r = TextReader::new(s, Options::DEFAULT);
+        let id = r.type_id()?;
+        r.finish(id)
+    }
+}
+
+impl FromStr for StdId {
+    type Err = ParseTypeError;
+
+    fn from_str(s: &str) -> ParseTypeResult<StdId> {
+        let mut r = TextReader::new(s, Options::DEFAULT);
+        let id = r.std_id()?;
+        r.finish(id)
+    }
+}
+
+impl FromStr for HashId {
+    type Err = ParseTypeError;
+
+    fn from_str(s: &str) -> ParseTypeResult<HashId> {
+        let mut r = TextReader::new(s, Options::DEFAULT);
+        let id = r.hash_id()?;
+        r.finish(id)
+    }
+}
+
+struct Printer<'a, 'f> {
+    f: &'a mut fmt::Formatter<'f>,
+    depth: usize,
+}
+
+impl Printer<'_, '_> {
+    fn seq<T>(&mut self, open: &str, close: &str, items: &[T], mut item: impl FnMut(&mut Self, &T) -> fmt::Result) -> fmt::Result {
+        self.f.write_str(open)?;
+        let pretty = self.f.alternate() && !items.is_empty();
+        self.depth += 1;
+        for (i, t) in items.iter().enumerate() {
+            if pretty {
+                self.f.write_str("\n")?;
+                for _ in 0..self.depth {
+                    self.f.write_str("    ")?;
+                }
+            } else if i != 0 {
+                self.f.write_str(", ")?;
+            }
+            item(self, t)?;
+            if pretty {
+                self.f.write_str(",")?;
+            }
+        }
+        self.depth -= 1;
+        if pretty {
+            self.f.write_str("\n")?;
+            for _ in 0..self.depth {
+                self.f.write_str("    ")?;
+            }
+        }
+        self.f.write_str(close)
+    }
+
+    fn value<B: AsRef<[u8]> + ByteStorage>(&mut self, val: &Value<B>) -> fmt::Result {
+        match val {
+            Value::Unit => self.f.write_str("unit"),
+            Value::Bool(v) => write!(self.f, "{}", v),
+            Value::U8(v) => write!(self.f, "{}u8", v),
+            Value::U16(v) => write!(self.f, "{}u16", v),
+            Value::U32(v) => write!(self.f, "{}u32", v),
+            Value::U64(v) => write!(self.f, "{}u64", v),
+            Value::I8(v) => write!(self.f, "{}i8", v),
+            Value::I16(v) => write!(self.f, "{}i16", v),
+            Value::I32(v) => write!(self.f, "{}i32", v),
+            Value::I64(v) => write!(self.f, "{}i64", v),
+            Value::F16(v) => {
+                let x = casting::f16_to_f32(*v);
+                if x.is_finite() { write!(self.f, "{:?}f16", x) } else { write!(self.f, "f16(0x{:04x})", v) }
+            }
+            Value::F32(v) => {
+                let x = f32::from_bits(*v);
+                if x.is_finite() { write!(self.f, "{:?}f32", x) } else { write!(self.f, "f32(0x{:08x})", v) }
+            }
+            Value::F64(v) => {
+                let x = f64::from_bits(*v);
+                if x.is_finite() { write!(self.f, "{:?}f64", x) } else { write!(self.f, "f64(0x{:016x})", v) }
+            }
+            Value::Char(v) => write!(self.f, "{:?}", v),
+            Value::U128(v) => write!(self.f, "{}u128", v),
+            Value::I128(v) => write!(self.f, "{}i128", v),
+            Value::String(s) => write!(self.f, "{:?}", AsRef::<str>::as_ref(s)),
+            Value::Bytes(b) => {
+                self.f.write_str("b\"")?;
+                for &c in b.as_ref() {
+                    match c {
+                        b'"' | b'\\' => write!(self.f, "\\{}", c as char)?,
+                        0x20..=0x7e => write!(self.f, "{}", c as char)?,
+                        c => write!(self.f, "\\x{:02x}", c)?,
+                    }
+                }
+                self.f.write_str("\"")
+            }
+            Value::Option(t, v) => match v.as_ref() {
+                Some(v) => {
+                    write!(self.f, "some<{}>(", t)?;
+                    self.value(v)?;
+                    self.f.write_str(")")
+                }
+                None => write!(self.f, "none<{}>", t),
+            },
+            Value::List(t, s) => {
+                write!(self.f, "list<{}>", t)?;
+                self.seq("[", "]", s, |p, v| p.value(v))
+            }
+            Value::Map((tk, tv), s) => {
+                write!(self.f, "map<{}, {}>", tk, tv)?;
+                self.seq("{", "}", s, |p, (k, v)| {
+                    p.value(k)?;
+                    p.f.write_str(": ")?;
+                    p.value(v)
+                })
+            }
+            Value::Tuple(s) => {
+                self.f.write_str("tuple")?;
+                self.seq("(", ")", s, |p, v| p.value(v))
+            }
+            Value::Alias(id, v) => {
+                write!(self.f, "{}(", Type::Alias(*id))?;
+                self.value(v)?;
+                self.f.write_str(")")
+            }
+            Value::CEnum(id, ev) => write!(self.f, "{}({})", Type::CEnum(*id), ev),
+            Value::Enum(id, ev, v) => {
+                write!(self.f, "{}({}, ", Type::Enum(*id), ev)?;
+                self.value(v)?;
+                self.f.write_str(")")
+            }
+            Value::Struct(id, s) => {
+                write!(self.f, "{}", Type::Struct(*id))?;
+                self.seq("(", ")", s, |p, v| p.value(v))
+            }
+            Value::Type(t) => write!(self.f, "type({})", t),
+            Value::TypeId(id) => write!(self.f, "typeid({})", id),
+        }
+    }
+}
+
+impl<B: AsRef<[u8]> + ByteStorage> fmt::Display for Value<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer { f, depth: 0 }.value(self)
+    }
+}
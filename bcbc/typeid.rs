@@ -4,6 +4,7 @@ pub const SCHEMA_ANONYMOUS: u8 = 0x00;
 pub const SCHEMA_HASH: u8 = 0xff;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TypeId {
     Anonymous,
     Std(StdId),
@@ -21,6 +22,8 @@ impl TypeId {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "crate::serde_impl::StdIdRepr"))]
 pub struct StdId {
     pub(crate) schema: u8,
     pub(crate) id: u16,
@@ -57,6 +60,7 @@ impl HashId {
         HashId { hash }
     }
 
+    // must be the same as `hash_path` in bcbc-derive, checked by the `derive` test
     #[allow(deprecated)]
     pub /* const */ fn from_path(path: &str) -> HashId {
         use core::hash::{Hash, Hasher, SipHasher};
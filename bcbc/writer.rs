@@ -1,160 +1,282 @@
 use foundations::byterepr::*;
 use super::*;
 
-// TODO writer error?
-
 struct Writer<O> {
     output: O,
+    opts: Options,
+    depth: usize,
+    // mirrors the reader
+    items: usize,
+    len: usize,
 }
 
 macro_rules! num_impl {
     ($($num:tt)*) => {$(
-        fn $num(&mut self, n: $num) {
-            self.bytes(n.to_bytes());
+        fn $num(&mut self, n: $num) -> EncodeResult<()> {
+            self.bytes(n.to_bytes())
         }
     )*};
 }
 
 impl<O: Output> Writer<O> {
-    fn new() -> Writer<O> {
-        Writer { output: Default::default() }
+    fn new(opts: Options) -> Writer<O> {
+        Writer { output: Default::default(), opts, depth: 0, items: 0, len: 0 }
     }
 
     fn into_inner(self) -> O::Storage {
         self.output.leak()
     }
 
+    fn grow(&mut self, size: usize) -> EncodeResult<()> {
+        let len = self.len.saturating_add(size);
+        if len > self.opts.max_output {
+            return Err(EncodeError::TooLongOutput(len));
+        }
+        self.len = len;
+        Ok(())
+    }
+
+    fn enter(&mut self) -> EncodeResult<()> {
+        if self.depth >= self.opts.max_depth {
+            return Err(EncodeError::TooDeep(self.depth + 1));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+
+    fn alloc(&mut self, size: usize) -> EncodeResult<()> {
+        let items = self.items.saturating_add(size);
+        if items > self.opts.max_alloc {
+            return Err(EncodeError::TooManyItems(items));
+        }
+        self.items = items;
+        Ok(())
+    }
+
     #[inline(always)]
-    fn bytes<B2: AsRef<[u8]>>(&mut self, bytes: B2) {
-        self.output.bytes(bytes);
+    fn bytes<B2: AsRef<[u8]>>(&mut self, bytes: B2) -> EncodeResult<()> {
+        self.grow(bytes.as_ref().len())?;
+        Ok(self.output.bytes(bytes)?)
     }
 
     #[inline(always)]
-    fn u8(&mut self, n: u8) {
-        self.output.byte(n);
+    fn u8(&mut self, n: u8) -> EncodeResult<()> {
+        self.grow(1)?;
+        Ok(self.output.byte(n)?)
     }
 
     num_impl! {
         u16 u32 u64
     }
 
-    fn typeid(&mut self, id: &TypeId) {
+    fn typeid(&mut self, id: &TypeId) -> EncodeResult<()> {
         // TODO(styling): as_h8 or in match blocks?
-        self.u8(id.as_h8());
+        self.u8(id.as_h8())?;
         match id {
             TypeId::Std(std_id) => {
-                self.u16(std_id.id());
+                self.u16(std_id.id())?;
             }
             TypeId::Hash(hash_id) => {
-                self.bytes(hash_id.hash());
+                self.bytes(hash_id.hash())?;
             }
             TypeId::Anonymous => {}
         }
+        Ok(())
     }
 
-    fn ty(&mut self, t: &Type) {
-        self.u8(t.as_tag() as u8);
-        match t {
-            Type::Unknown
-            | Type::Unit
-            | Type::Bool
-            | Type::U8
-            | Type::U16
-            | Type::U32
-            | Type::U64
-            | Type::I8
-            | Type::I16
-            | Type::I32
-            | Type::I64
-            | Type::F16
-            | Type::F32
-            | Type::F64
-            | Type::String
-            | Type::Bytes
-            | Type::Type
-            | Type::TypeId => {}
-
-            Type::Option(t)
-            | Type::List(t) => {
-                self.ty(t);
-            }
-            Type::Map(tk, tv) => {
-                self.ty(tk);
-                self.ty(tv);
-            }
+    // iterative, so that nesting is only bounded by opts.max_depth instead of the call stack
+    fn ty(&mut self, t: &Type) -> EncodeResult<()> {
+        let mut stack: Vec<TypeChildren<'_>> = Vec::new();
+        let mut next = Some(t);
+        loop {
+            if let Some(t) = next.take() {
+                self.u8(t.as_tag() as u8)?;
+                let children = match t {
+                    Type::Unknown
+                    | Type::Unit
+                    | Type::Bool
+                    | Type::U8
+                    | Type::U16
+                    | Type::U32
+                    | Type::U64
+                    | Type::I8
+                    | Type::I16
+                    | Type::I32
+                    | Type::I64
+                    | Type::F16
+                    | Type::F32
+                    | Type::F64
+                    | Type::Char
+                    | Type::U128
+                    | Type::I128
+                    | Type::String
+                    | Type::Bytes
+                    | Type::Type
+                    | Type::TypeId => None,
 
-            Type::Tuple(s) => {
-                // should checked in type check
-                self.u8(s.len().try_into().unwrap());
-                for t in s.as_ref() {
-                    self.ty(t);
+                    Type::Option(t)
+                    | Type::List(t) => {
+                        self.enter()?;
+                        Some(TypeChildren::Pair(Some(t), None))
+                    }
+                    Type::Map(tk, tv) => {
+                        self.enter()?;
+                        Some(TypeChildren::Pair(Some(tk), Some(tv)))
+                    }
+
+                    Type::Tuple(s) => {
+                        let len = s.len().try_into().map_err(|_| EncodeError::TooLongTuple(s.len()))?;
+                        self.u8(len)?;
+                        self.enter()?;
+                        self.alloc(s.len())?;
+                        Some(TypeChildren::Seq(s.iter()))
+                    }
+
+                    Type::Alias(r)
+                    | Type::CEnum(r)
+                    | Type::Enum(r)
+                    | Type::Struct(r) => {
+                        self.typeid(r)?;
+                        None
+                    }
+                };
+                if let Some(children) = children {
+                    stack.push(children);
                 }
             }
-
-            Type::Alias(r)
-            | Type::CEnum(r)
-            | Type::Enum(r)
-            | Type::Struct(r) => {
-                self.typeid(r);
+            let Some(children) = stack.last_mut() else {
+                return Ok(());
+            };
+            match children.next() {
+                Some(t) => next = Some(t),
+                None => {
+                    let _ = stack.pop();
+                    self.leave();
+                }
             }
         }
     }
 
     #[inline]
-    fn header(&mut self, h4: H4, l4: L4) {
-        self.u8(casting::from_h4l4(h4, l4));
+    fn header(&mut self, h4: H4, l4: L4) -> EncodeResult<()> {
+        self.u8(casting::from_h4l4(h4, l4))
+    }
+
+    fn ext1(&mut self, ext1: Ext1) -> EncodeResult<()> {
+        self.header(H4::from_ext1(ext1), L4::EXT1)
+    }
+
+    fn ext2(&mut self, ext2: Ext2) -> EncodeResult<()> {
+        self.header(H4::from_ext2(ext2), L4::EXT2)
+    }
+
+    fn some_start(&mut self, t: &Type) -> EncodeResult<()> {
+        self.ext1(Ext1::Some)?;
+        self.ty(t)?;
+        Ok(())
+    }
+
+    fn list_start(&mut self, t: &Type, len: usize) -> EncodeResult<()> {
+        self.extszvar(H4::List, len)?;
+        self.ty(t)?;
+        Ok(())
     }
 
-    fn ext1(&mut self, ext1: Ext1) {
-        self.header(H4::from_ext1(ext1), L4::EXT1);
+    fn map_start(&mut self, tk: &Type, tv: &Type, len: usize) -> EncodeResult<()> {
+        self.extszvar(H4::Map, len)?;
+        self.ty(tk)?;
+        self.ty(tv)?;
+        Ok(())
     }
 
-    fn extvar(&mut self, h4: H4, u: u64) {
+    fn tuple_start(&mut self, len: usize) -> EncodeResult<()> {
+        self.extszvar(H4::Tuple, len)
+    }
+
+    fn alias_start(&mut self, r: &TypeId) -> EncodeResult<()> {
+        self.ext1(Ext1::Alias)?;
+        self.typeid(r)?;
+        Ok(())
+    }
+
+    fn enum_start(&mut self, r: &TypeId, ev: EnumVariantId) -> EncodeResult<()> {
+        self.extvar(H4::Enum, ev)?;
+        self.typeid(r)?;
+        Ok(())
+    }
+
+    fn struct_start(&mut self, r: &TypeId, len: usize) -> EncodeResult<()> {
+        self.extszvar(H4::Struct, len)?;
+        self.typeid(r)?;
+        Ok(())
+    }
+
+    fn extvar(&mut self, h4: H4, u: u64) -> EncodeResult<()> {
         // TODO casting using overflow protected methods?
         if u < (EXT8 as u64) {
-            self.header(h4, (u as u8).try_into().unwrap());
+            self.header(h4, (u as u8).try_into()?)?;
         } else if u <= (u8::MAX as u64) {
-            self.header(h4, EXT8);
-            self.u8(u as u8);
+            self.header(h4, EXT8)?;
+            self.u8(u as u8)?;
         } else if u <= (u16::MAX as u64) {
-            self.header(h4, EXT16);
-            self.u16(u as u16);
+            self.header(h4, EXT16)?;
+            self.u16(u as u16)?;
         } else if u <= (u32::MAX as u64) {
-            self.header(h4, EXT32);
-            self.u32(u as u32);
+            self.header(h4, EXT32)?;
+            self.u32(u as u32)?;
         } else {
-            self.header(h4, EXT64);
-            self.u64(u);
+            self.header(h4, EXT64)?;
+            self.u64(u)?;
         }
+        Ok(())
     }
 
-    fn extszvar(&mut self, h4: H4, sz: usize) {
-        self.extvar(h4, sz.try_into().map_err(|_| Fatal::FromSize(sz)).unwrap())
-    }
-
-    // TODO recv Iterator
-    fn val_seq<B: AsRef<[u8]>>(&mut self, s: &[Value<B>]) {
-        for v in s {
-            self.val(v);
+    fn extszvar(&mut self, h4: H4, sz: usize) -> EncodeResult<()> {
+        // usize is never wider than u64
+        let u = sz as u64;
+        if !self.opts.len_allowed(u) {
+            return Err(EncodeError::TooLongLen(u));
         }
+        self.extvar(h4, u)
     }
 
-    fn val_seq_map<B: AsRef<[u8]>>(&mut self, s: &[(Value<B>, Value<B>)]) {
-        for (k, v) in s {
-            self.val(k);
-            self.val(v);
+    // iterative like ty
+    fn val<B: AsRef<[u8]>>(&mut self, val: &Value<B>) -> EncodeResult<()> {
+        let mut stack: Vec<Children<'_, B>> = Vec::new();
+        let mut next = Some(val);
+        loop {
+            if let Some(v) = next.take() {
+                if let Some(children) = self.head(v)? {
+                    stack.push(children);
+                }
+            }
+            let Some(children) = stack.last_mut() else {
+                return Ok(());
+            };
+            match children.next() {
+                Some(v) => next = Some(v),
+                None => {
+                    let _ = stack.pop();
+                    self.leave();
+                }
+            }
         }
     }
 
-    fn val<B: AsRef<[u8]>>(&mut self, val: &Value<B>) {
+    // writes a scalar, or the start of a container and enters it, returning its inner values
+    fn head<'a, B: AsRef<[u8]>>(&mut self, val: &'a Value<B>) -> EncodeResult<Option<Children<'a, B>>> {
         macro_rules! bytevar_impl {
             ($n:expr, $nty:tt, $l4:expr, $rangefn:expr, $lenfn:expr) => {
                 let mut buf = [0; 8];
                 const NLEN: usize = core::mem::size_of::<$nty>();
                 buf[$rangefn(NLEN)].copy_from_slice(&$n.to_bytes());
                 let len = $lenfn(&buf);
-                self.header(H4::from_bytevar_len(len).unwrap(), $l4);
-                self.bytes(&buf[$rangefn(len)]);
+                self.header(H4::from_bytevar_len(len)?, $l4)?;
+                self.bytes(&buf[$rangefn(len)])?;
             };
             (U: $n:expr, $nty:tt, $l4:expr) => {{
                 bytevar_impl!(
@@ -203,6 +325,7 @@ impl<O: Output> Writer<O> {
             };
         }
 
+        let mut children = None;
         numval_impl! {
             U {
                 U8 u8
@@ -224,83 +347,396 @@ impl<O: Output> Writer<O> {
                 F64 u64
             }
             Value::Unit => {
-                self.ext1(Ext1::Unit);
+                self.ext1(Ext1::Unit)?;
             },
             Value::Bool(b) => {
                 if *b {
-                    self.ext1(Ext1::True)
+                    self.ext1(Ext1::True)?;
                 } else {
-                    self.ext1(Ext1::False);
+                    self.ext1(Ext1::False)?;
                 }
             },
+            Value::Char(c) => {
+                self.ext2(Ext2::Char)?;
+                self.u32(*c as u32)?;
+            },
+            Value::U128(u) => {
+                self.ext2(Ext2::U128)?;
+                self.bytes(u.to_be_bytes())?;
+            },
+            Value::I128(i) => {
+                self.ext2(Ext2::I128)?;
+                self.bytes(i.to_be_bytes())?;
+            },
             Value::String(b) => {
-                self.extszvar(H4::String, b.as_ref().len());
-                self.bytes(b.as_ref());
+                self.extszvar(H4::String, b.as_ref().len())?;
+                self.bytes(b.as_ref())?;
             },
             Value::Bytes(b) => {
-                self.extszvar(H4::Bytes, b.as_ref().len());
-                self.bytes(b);
+                self.extszvar(H4::Bytes, b.as_ref().len())?;
+                self.bytes(b)?;
             },
             Value::Option(t, opt) => {
                 if let Some(v) = opt.as_ref() {
-                    self.ext1(Ext1::Some);
-                    self.ty(t);
-                    self.val(v);
+                    self.some_start(t)?;
+                    self.enter()?;
+                    children = Some(Children::one(v));
                 } else {
-                    self.ext1(Ext1::None);
-                    self.ty(t);
+                    self.ext1(Ext1::None)?;
+                    self.ty(t)?;
                 }
             },
             Value::List(t, s) => {
-                self.extszvar(H4::List, s.len());
-                self.ty(t);
-                self.val_seq(s);
+                self.list_start(t, s.len())?;
+                self.enter()?;
+                self.alloc(s.len())?;
+                children = Some(Children::Seq(s.iter()));
             },
             Value::Map((tk, tv), s) => {
-                self.extszvar(H4::Map, s.len());
-                self.ty(tk);
-                self.ty(tv);
-                self.val_seq_map(s);
+                self.map_start(tk, tv, s.len())?;
+                self.enter()?;
+                self.alloc(s.len())?;
+                children = Some(Children::Map(s.iter(), None));
             },
             Value::Tuple(s) => {
-                self.extszvar(H4::Tuple, s.len());
-                self.val_seq(s);
+                self.tuple_start(s.len())?;
+                self.enter()?;
+                self.alloc(s.len())?;
+                children = Some(Children::Seq(s.iter()));
             },
             Value::Alias(r, v) => {
-                self.ext1(Ext1::Alias);
-                self.typeid(r);
-                self.val(v);
+                self.alias_start(r)?;
+                self.enter()?;
+                children = Some(Children::one(v));
             },
             Value::CEnum(r, ev) => {
-                self.extvar(H4::CEnum, *ev);
-                self.typeid(r);
+                self.extvar(H4::CEnum, *ev)?;
+                self.typeid(r)?;
             },
             Value::Enum(r, ev, v) => {
-                self.extvar(H4::Enum, *ev);
-                self.typeid(r);
-                self.val(v);
+                self.enum_start(r, *ev)?;
+                self.enter()?;
+                children = Some(Children::one(v));
             },
             Value::Struct(r, s) => {
-                self.extszvar(H4::Struct, s.len());
-                self.typeid(r);
-                self.val_seq(s);
+                self.struct_start(r, s.len())?;
+                self.enter()?;
+                self.alloc(s.len())?;
+                children = Some(Children::Seq(s.iter()));
             },
             Value::Type(t) => {
-                self.ext1(Ext1::Type);
-                self.ty(t);
+                self.ext1(Ext1::Type)?;
+                self.ty(t)?;
             },
             Value::TypeId(r) => {
-                self.ext1(Ext1::TypeId);
-                self.typeid(r);
+                self.ext1(Ext1::TypeId)?;
+                self.typeid(r)?;
             },
         }
+        Ok(children)
+    }
+}
+
+// what is left to write of a container type
+enum TypeChildren<'a> {
+    Pair(Option<&'a Type>, Option<&'a Type>),
+    Seq(core::slice::Iter<'a, Type>),
+}
+
+impl<'a> Iterator for TypeChildren<'a> {
+    type Item = &'a Type;
+
+    fn next(&mut self) -> Option<&'a Type> {
+        match self {
+            TypeChildren::Pair(a, b) => a.take().or_else(|| b.take()),
+            TypeChildren::Seq(it) => it.next(),
+        }
+    }
+}
+
+// what is left to write of a container value, map entries are flattened into key and value
+enum Children<'a, B> {
+    Seq(core::slice::Iter<'a, Value<B>>),
+    Map(core::slice::Iter<'a, (Value<B>, Value<B>)>, Option<&'a Value<B>>),
+}
+
+impl<'a, B> Children<'a, B> {
+    fn one(v: &'a Value<B>) -> Children<'a, B> {
+        Children::Seq(core::slice::from_ref(v).iter())
+    }
+}
+
+impl<'a, B> Iterator for Children<'a, B> {
+    type Item = &'a Value<B>;
+
+    fn next(&mut self) -> Option<&'a Value<B>> {
+        match self {
+            Children::Seq(it) => it.next(),
+            Children::Map(it, pending) => match pending.take() {
+                Some(v) => Some(v),
+                None => it.next().map(|(k, v)| {
+                    *pending = Some(v);
+                    k
+                }),
+            },
+        }
+    }
+}
+
+// discards everything written
+#[derive(Default)]
+struct NullOutput;
+
+impl Output for NullOutput {
+    type Storage = [u8; 0];
+
+    #[inline(always)]
+    fn byte(&mut self, _n: u8) -> core::result::Result<(), WriteError> {
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn bytes<B: AsRef<[u8]>>(&mut self, _bytes: B) -> core::result::Result<(), WriteError> {
+        Ok(())
+    }
+
+    fn leak(self) -> [u8; 0] {
+        []
     }
 }
 
 impl<B: AsRef<[u8]>> Value<B> {
-    pub fn encode<O: Output>(&self) -> O::Storage {
-        let mut writer = Writer::<O>::new();
-        writer.val(self);
-        writer.into_inner()
+    pub fn encode<O: Output>(&self) -> EncodeResult<O::Storage> {
+        self.encode_with::<O>(Options::DEFAULT)
+    }
+
+    pub fn encode_with<O: Output>(&self, opts: Options) -> EncodeResult<O::Storage> {
+        if opts.typecheck {
+            self.typecheck()?;
+        }
+        let mut writer = Writer::<O>::new(opts);
+        writer.val(self)?;
+        Ok(writer.into_inner())
+    }
+
+    // runs exactly the same checks as `encode` without writing anything
+    pub fn check_encode(&self) -> EncodeResult<()> {
+        self.check_encode_with(Options::DEFAULT)
+    }
+
+    pub fn check_encode_with(&self, opts: Options) -> EncodeResult<()> {
+        let _ = self.encode_with::<NullOutput>(opts)?;
+        Ok(())
+    }
+}
+
+struct Frame {
+    // for maps, keys and values are counted separately
+    len: usize,
+    written: usize,
+    // inner values are checked against with `opts.typecheck`
+    ty: Type,
+    // of the first inner value, those of the others follow with `PathSeg::nth`
+    first: PathSeg,
+}
+
+pub struct StreamWriter<O> {
+    writer: Writer<O>,
+    // the first one is the top-level value
+    stack: Vec<Frame>,
+}
+
+macro_rules! scalar_impl {
+    ($($fn_name:ident($ty:ty) => $variant:ident)*) => {$(
+        pub fn $fn_name(&mut self, n: $ty) -> EncodeResult<()> {
+            self.value(&Value::<&[u8]>::$variant(n))
+        }
+    )*};
+}
+
+impl<O: Output> StreamWriter<O> {
+    pub fn new() -> StreamWriter<O> {
+        StreamWriter::with_options(Options::DEFAULT)
+    }
+
+    pub fn with_options(opts: Options) -> StreamWriter<O> {
+        StreamWriter {
+            writer: Writer::new(opts),
+            stack: vec![Frame { len: 1, written: 0, ty: Type::Unknown, first: PathSeg::Some }],
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.stack.len() - 1
+    }
+
+    // counts an inner value of the innermost open container, and with `opts.typecheck` checks it against the type
+    // declared there by `check`, which returns the type to check its inner values against like `Event::check`
+    fn item(&mut self, check: impl FnOnce(&Type) -> CheckResult<Type>) -> EncodeResult<Type> {
+        // the top-level frame is never popped
+        let Some((frame, outer)) = self.stack.split_last() else {
+            return Err(EncodeError::UnexpectedEnd);
+        };
+        if frame.written == frame.len {
+            return Err(EncodeError::LenMismatch { len: frame.len, written: frame.len + 1 });
+        }
+        let ty = if self.writer.opts.typecheck {
+            check(&frame.ty.inner(frame.written)).map_err(|err| {
+                // the top-level value has no segment, the outer containers are at their last written inner values
+                let outer_segs = outer.iter().skip(1).map(|f| f.first.nth(f.written - 1));
+                err.within(outer_segs.chain((!outer.is_empty()).then(|| frame.first.nth(frame.written))))
+            })?
+        } else {
+            Type::Unknown
+        };
+        // only counted once it is known to fit
+        self.stack.last_mut().unwrap().written += 1;
+        Ok(ty)
+    }
+
+    // like `Writer::enter`, fails beyond `max_depth`
+    fn enter(&mut self, len: usize, ty: Type, first: PathSeg) -> EncodeResult<()> {
+        self.writer.enter()?;
+        self.stack.push(Frame { len, written: 0, ty, first });
+        Ok(())
+    }
+
+    pub fn value<B: AsRef<[u8]>>(&mut self, val: &Value<B>) -> EncodeResult<()> {
+        let _ = self.item(|t| val.check(t).map(|()| Type::Unknown))?;
+        self.writer.val(val)
+    }
+
+    scalar_impl! {
+        bool(bool) => Bool
+        u8(u8)     => U8
+        u16(u16)   => U16
+        u32(u32)   => U32
+        u64(u64)   => U64
+        i8(i8)     => I8
+        i16(i16)   => I16
+        i32(i32)   => I32
+        i64(i64)   => I64
+        f16(u16)   => F16
+        f32(u32)   => F32
+        f64(u64)   => F64
+        char(char) => Char
+        u128(u128) => U128
+        i128(i128) => I128
+        ty(Type)   => Type
+        type_id(TypeId) => TypeId
+    }
+
+    pub fn unit(&mut self) -> EncodeResult<()> {
+        self.value(&Value::<&[u8]>::Unit)
+    }
+
+    pub fn string(&mut self, s: &str) -> EncodeResult<()> {
+        self.value(&Value::String(ByteStr::<&[u8]>::from(s)))
+    }
+
+    pub fn bytes(&mut self, b: &[u8]) -> EncodeResult<()> {
+        self.value(&Value::Bytes(b))
+    }
+
+    pub fn none(&mut self, t: &Type) -> EncodeResult<()> {
+        let _ = self.item(|e| Value::<&[u8]>::Option(t.clone(), Box::new(None)).check(e).map(|()| Type::Unknown))?;
+        self.writer.ext1(Ext1::None)?;
+        self.writer.ty(t)
+    }
+
+    pub fn c_enum(&mut self, id: TypeId, ev: EnumVariantId) -> EncodeResult<()> {
+        self.value(&Value::<&[u8]>::CEnum(id, ev))
+    }
+
+    pub fn begin_some(&mut self, t: &Type) -> EncodeResult<()> {
+        let ty = self.item(|e| Event::<&[u8]>::SomeStart { ty: t.clone() }.check(e))?;
+        self.writer.some_start(t)?;
+        self.enter(1, ty, PathSeg::Some)
+    }
+
+    pub fn begin_list(&mut self, t: &Type, len: usize) -> EncodeResult<()> {
+        let ty = self.item(|e| Event::<&[u8]>::ListStart { len, ty: t.clone() }.check(e))?;
+        self.writer.list_start(t, len)?;
+        self.writer.alloc(len)?;
+        self.enter(len, ty, PathSeg::Index(SeqKind::List, 0))
+    }
+
+    pub fn begin_map(&mut self, tk: &Type, tv: &Type, len: usize) -> EncodeResult<()> {
+        let items = len.checked_mul(2).ok_or(EncodeError::TooLongLen(len as u64))?;
+        let ty = self.item(|e| Event::<&[u8]>::MapStart { len, tk: tk.clone(), tv: tv.clone() }.check(e))?;
+        self.writer.map_start(tk, tv, len)?;
+        self.writer.alloc(len)?;
+        self.enter(items, ty, PathSeg::Key(0))
+    }
+
+    pub fn begin_tuple(&mut self, len: usize) -> EncodeResult<()> {
+        let ty = self.item(|e| Event::<&[u8]>::TupleStart { len }.check(e))?;
+        self.writer.tuple_start(len)?;
+        self.writer.alloc(len)?;
+        self.enter(len, ty, PathSeg::Index(SeqKind::Tuple, 0))
+    }
+
+    pub fn begin_alias(&mut self, id: TypeId) -> EncodeResult<()> {
+        let ty = self.item(|e| Event::<&[u8]>::AliasStart { id }.check(e))?;
+        self.writer.alias_start(&id)?;
+        self.enter(1, ty, PathSeg::Alias(id))
+    }
+
+    pub fn begin_enum(&mut self, id: TypeId, ev: EnumVariantId) -> EncodeResult<()> {
+        let ty = self.item(|e| Event::<&[u8]>::EnumStart { id, ev }.check(e))?;
+        self.writer.enum_start(&id, ev)?;
+        self.enter(1, ty, PathSeg::Variant(id, ev))
+    }
+
+    pub fn begin_struct(&mut self, id: TypeId, len: usize) -> EncodeResult<()> {
+        let ty = self.item(|e| Event::<&[u8]>::StructStart { id, len }.check(e))?;
+        self.writer.struct_start(&id, len)?;
+        self.writer.alloc(len)?;
+        self.enter(len, ty, PathSeg::Index(SeqKind::Struct(id), 0))
+    }
+
+    pub fn end(&mut self) -> EncodeResult<()> {
+        if self.depth() == 0 {
+            return Err(EncodeError::UnexpectedEnd);
+        }
+        if let Some(Frame { len, written, .. }) = self.stack.pop() {
+            self.writer.leave();
+            if len != written {
+                return Err(EncodeError::LenMismatch { len, written });
+            }
+        }
+        Ok(())
+    }
+
+    // e.g. piping from `StreamReader`
+    pub fn event<B: AsRef<[u8]>>(&mut self, ev: &Event<B>) -> EncodeResult<()> {
+        match ev {
+            Event::Scalar(v) => self.value(v),
+            Event::SomeStart { ty } => self.begin_some(ty),
+            Event::ListStart { len, ty } => self.begin_list(ty, *len),
+            Event::MapStart { len, tk, tv } => self.begin_map(tk, tv, *len),
+            Event::TupleStart { len } => self.begin_tuple(*len),
+            Event::AliasStart { id } => self.begin_alias(*id),
+            Event::EnumStart { id, ev } => self.begin_enum(*id, *ev),
+            Event::StructStart { id, len } => self.begin_struct(*id, *len),
+            Event::End => self.end(),
+        }
+    }
+
+    pub fn finish(self) -> EncodeResult<O::Storage> {
+        let depth = self.depth();
+        if depth != 0 {
+            return Err(EncodeError::Unfinished { depth });
+        }
+        match self.stack.first() {
+            Some(Frame { len: 1, written: 1, .. }) => Ok(self.writer.into_inner()),
+            _ => Err(EncodeError::Unfinished { depth }),
+        }
+    }
+}
+
+impl<O: Output> Default for StreamWriter<O> {
+    fn default() -> Self {
+        Self::new()
     }
 }
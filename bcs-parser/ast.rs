@@ -0,0 +1,66 @@
+use bcbc::{EnumVariantId, Type};
+
+// as written, resolved with `Schema::resolve`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schema {
+    pub decls: Vec<Decl>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decl {
+    pub name: String,
+    pub id: IdExpr,
+    pub kind: DeclKind,
+}
+
+// `std(0x01, 0x5f50)` or `hash("path")`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdExpr {
+    Std { schema: u8, id: u16 },
+    Hash(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeclKind {
+    // `alias Name id = type;`
+    Alias(TypeExpr),
+    // `struct Name id { name: type, .. }`
+    Struct(Vec<FieldDecl>),
+    // `enum Name id { Name(type) = 1, Name, .. }`
+    Enum(Vec<VariantDecl>),
+    // `cenum Name id { Name = 1, Name, .. }`
+    CEnum(Vec<CVariantDecl>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDecl {
+    pub name: String,
+    pub ty: TypeExpr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantDecl {
+    pub name: String,
+    // counting up from the previous one if not written
+    pub id: Option<EnumVariantId>,
+    // `unit` if not written
+    pub ty: Option<TypeExpr>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CVariantDecl {
+    pub name: String,
+    pub id: Option<EnumVariantId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeExpr {
+    // keywords, e.g. `u8`, `string` or `unknown`, only ever the variants without inner types
+    Scalar(Type),
+    Option(Box<TypeExpr>),
+    List(Box<TypeExpr>),
+    Map(Box<TypeExpr>, Box<TypeExpr>),
+    Tuple(Vec<TypeExpr>),
+    // declared types
+    Named(String),
+}
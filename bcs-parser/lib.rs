@@ -1,4 +1,13 @@
 #![deny(unused_results)]
 
+mod ast;
+pub use ast::*;
+mod parser;
+pub use parser::*;
+mod resolve;
+pub use resolve::*;
+mod value;
+pub use value::*;
+
 #[cfg(test)]
 mod tests;
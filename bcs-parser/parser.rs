@@ -0,0 +1,258 @@
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_until, take_while, take_while1},
+    character::complete::{char, digit1, hex_digit1, multispace1},
+    combinator::{cut, eof, map, map_res, opt, recognize, value},
+    error::{ContextError, ErrorKind, FromExternalError, ParseError as NomParseError},
+    multi::{many0, separated_list0},
+    sequence::{delimited, pair, preceded, terminated, tuple},
+    IResult,
+};
+use bcbc::{Tag, Type};
+use crate::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    // in bytes
+    pub pos: usize,
+    pub expected: &'static str,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "expected {} at {}", self.expected, self.pos)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// keeps the rest of the input to compute the position
+#[derive(Debug)]
+pub(crate) struct Error<'a> {
+    pub(crate) rest: &'a str,
+    pub(crate) expected: &'static str,
+}
+
+impl<'a> NomParseError<&'a str> for Error<'a> {
+    // all parsers are wrapped in `context`, which names the error
+    fn from_error_kind(rest: &'a str, _kind: ErrorKind) -> Self {
+        Error { rest, expected: "valid syntax" }
+    }
+
+    // keeps the innermost error
+    fn append(_rest: &'a str, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    // keeps the furthest error
+    fn or(self, other: Self) -> Self {
+        if other.rest.len() <= self.rest.len() { other } else { self }
+    }
+}
+
+impl<'a, E> FromExternalError<&'a str, E> for Error<'a> {
+    fn from_external_error(rest: &'a str, kind: ErrorKind, _e: E) -> Self {
+        Self::from_error_kind(rest, kind)
+    }
+}
+
+impl<'a> ContextError<&'a str> for Error<'a> {
+    // names what failed at the same position (ignoring whitespace), so an unexpected `}` after `x:` is
+    // "type" and not "identifier"
+    fn add_context(rest: &'a str, ctx: &'static str, other: Self) -> Self {
+        let rest = skip(rest).map_or(rest, |(rest, _)| rest);
+        if other.rest.len() == rest.len() {
+            Error { rest, expected: ctx }
+        } else {
+            other
+        }
+    }
+}
+
+pub(crate) type PResult<'a, T> = IResult<&'a str, T, Error<'a>>;
+
+// maximum nesting of types and values, the same as decoding with `bcbc::Options::DEFAULT`, so that parsing does not overflow the
+// stack
+pub const MAX_DEPTH: usize = bcbc::Options::DEFAULT.max_depth;
+
+// fails without backtracking when opening a container `depth` containers deep is not allowed
+pub(crate) fn enter<'a>(input: &'a str, depth: usize, expected: &'static str) -> PResult<'a, ()> {
+    if depth >= MAX_DEPTH {
+        let rest = skip(input).map_or(input, |(rest, _)| rest);
+        return Err(nom::Err::Failure(Error { rest, expected }));
+    }
+    Ok((input, ()))
+}
+
+pub(crate) fn context<'a, T>(ctx: &'static str, f: impl FnMut(&'a str) -> PResult<'a, T>) -> impl FnMut(&'a str) -> PResult<'a, T> {
+    nom::error::context(ctx, f)
+}
+
+// whitespace and `//` comments
+pub(crate) fn skip(input: &str) -> PResult<'_, ()> {
+    value((), many0(alt((
+        value((), multispace1),
+        value((), pair(tag("//"), take_while(|c| c != '\n'))),
+    ))))(input)
+}
+
+pub(crate) fn ws<'a, T>(f: impl FnMut(&'a str) -> PResult<'a, T>) -> impl FnMut(&'a str) -> PResult<'a, T> {
+    preceded(skip, f)
+}
+
+pub(crate) fn sym<'a>(c: char) -> impl FnMut(&'a str) -> PResult<'a, char> {
+    let ctx = match c {
+        '(' => "`(`",
+        ')' => "`)`",
+        '<' => "`<`",
+        '>' => "`>`",
+        '{' => "`{`",
+        '}' => "`}`",
+        '[' => "`[`",
+        ']' => "`]`",
+        ',' => "`,`",
+        ':' => "`:`",
+        ';' => "`;`",
+        '=' => "`=`",
+        _ => unreachable!(),
+    };
+    context(ctx, ws(char(c)))
+}
+
+pub(crate) fn ident(input: &str) -> PResult<'_, &str> {
+    context("identifier", ws(recognize(pair(
+        take_while1(|c: char| c.is_ascii_alphabetic() || c == '_'),
+        take_while(|c: char| c.is_ascii_alphanumeric() || c == '_'),
+    ))))(input)
+}
+
+// a whole identifier, so `structs` is not `struct`
+fn keyword<'a>(kw: &'static str) -> impl FnMut(&'a str) -> PResult<'a, &'a str> {
+    move |input| {
+        let (input, ()) = skip(input)?;
+        let (rest, id) = ident(input)?;
+        if id == kw {
+            Ok((rest, id))
+        } else {
+            Err(nom::Err::Error(Error { rest: input, expected: kw }))
+        }
+    }
+}
+
+// `0x` prefixed hex or decimal
+pub(crate) fn number<T: TryFrom<u64>>(input: &str) -> PResult<'_, T> {
+    context("number", ws(map_res(
+        alt((
+            map_res(preceded(tag("0x"), hex_digit1), |s| u64::from_str_radix(s, 16)),
+            map_res(digit1, str::parse::<u64>),
+        )),
+        T::try_from,
+    )))(input)
+}
+
+fn string(input: &str) -> PResult<'_, &str> {
+    context("string", ws(delimited(char('"'), take_until("\""), char('"'))))(input)
+}
+
+fn type_id(input: &str) -> PResult<'_, IdExpr> {
+    context("type id", alt((
+        preceded(keyword("std"), cut(map(
+            delimited(sym('('), tuple((number, sym(','), number)), sym(')')),
+            |(schema, _, id)| IdExpr::Std { schema, id },
+        ))),
+        preceded(keyword("hash"), cut(map(
+            delimited(sym('('), string, sym(')')),
+            |path| IdExpr::Hash(path.to_owned()),
+        ))),
+    )))(input)
+}
+
+fn generic<'a, T>(f: impl FnMut(&'a str) -> PResult<'a, T>) -> impl FnMut(&'a str) -> PResult<'a, T> {
+    cut(delimited(sym('<'), f, sym('>')))
+}
+
+pub(crate) fn list_of<'a, T>(f: impl FnMut(&'a str) -> PResult<'a, T>) -> impl FnMut(&'a str) -> PResult<'a, Vec<T>> {
+    terminated(separated_list0(sym(','), f), opt(sym(',')))
+}
+
+// a type inside `depth` generic types
+fn type_at<'a>(depth: usize) -> impl FnMut(&'a str) -> PResult<'a, TypeExpr> {
+    move |input| type_expr(input, depth)
+}
+
+fn type_expr(input: &str, depth: usize) -> PResult<'_, TypeExpr> {
+    let (rest, name) = context("type", ident)(input)?;
+    let boxed = |t| Box::new(t);
+    let inner = type_at(depth + 1);
+    if let "option" | "list" | "map" | "tuple" = name {
+        let _ = enter(input, depth, "type within the depth limit")?;
+    }
+    match name {
+        "option" => map(generic(inner), |t| TypeExpr::Option(boxed(t)))(rest),
+        "list" => map(generic(inner), |t| TypeExpr::List(boxed(t)))(rest),
+        "map" => map(generic(tuple((inner, sym(','), type_at(depth + 1)))), |(k, _, v)| TypeExpr::Map(boxed(k), boxed(v)))(rest),
+        "tuple" => map(generic(list_of(inner)), TypeExpr::Tuple)(rest),
+        name => match Tag::from_name(name).and_then(Type::from_scalar_tag) {
+            Some(t) => Ok((rest, TypeExpr::Scalar(t))),
+            None => Ok((rest, TypeExpr::Named(name.to_owned()))),
+        },
+    }
+}
+
+fn field(input: &str) -> PResult<'_, FieldDecl> {
+    context("field", map(
+        tuple((ident, cut(sym(':')), cut(type_at(0)))),
+        |(name, _, ty)| FieldDecl { name: name.to_owned(), ty },
+    ))(input)
+}
+
+fn variant_id(input: &str) -> PResult<'_, Option<u64>> {
+    opt(preceded(sym('='), cut(number)))(input)
+}
+
+fn variant(input: &str) -> PResult<'_, VariantDecl> {
+    context("variant", map(
+        tuple((ident, opt(delimited(sym('('), cut(type_at(0)), cut(sym(')')))), variant_id)),
+        |(name, ty, id)| VariantDecl { name: name.to_owned(), id, ty },
+    ))(input)
+}
+
+fn c_variant(input: &str) -> PResult<'_, CVariantDecl> {
+    context("variant", map(
+        pair(ident, variant_id),
+        |(name, id)| CVariantDecl { name: name.to_owned(), id },
+    ))(input)
+}
+
+fn body<'a, T>(f: impl FnMut(&'a str) -> PResult<'a, T>) -> impl FnMut(&'a str) -> PResult<'a, Vec<T>> {
+    delimited(sym('{'), list_of(f), sym('}'))
+}
+
+fn decl(input: &str) -> PResult<'_, Decl> {
+    let (rest, kw) = context("declaration", alt((keyword("alias"), keyword("struct"), keyword("enum"), keyword("cenum"))))(input)?;
+    let (rest, (name, id)) = cut(pair(ident, type_id))(rest)?;
+    let (rest, kind) = cut(|rest| match kw {
+        "alias" => map(delimited(sym('='), type_at(0), sym(';')), DeclKind::Alias)(rest),
+        "struct" => map(body(field), DeclKind::Struct)(rest),
+        "enum" => map(body(variant), DeclKind::Enum)(rest),
+        _ => map(body(c_variant), DeclKind::CEnum)(rest),
+    })(rest)?;
+    Ok((rest, Decl { name: name.to_owned(), id, kind }))
+}
+
+pub(crate) fn finish<'a, T>(input: &'a str, res: PResult<'a, T>) -> Result<T, ParseError> {
+    match res {
+        Ok((_, t)) => Ok(t),
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => Err(ParseError { pos: input.len() - e.rest.len(), expected: e.expected }),
+        Err(nom::Err::Incomplete(_)) => unreachable!(),
+    }
+}
+
+pub fn parse(input: &str) -> Result<Schema, ParseError> {
+    let res = terminated(many0(decl), context("declaration", ws(eof)))(input);
+    finish(input, res).map(|decls| Schema { decls })
+}
+
+pub fn parse_type(input: &str) -> Result<TypeExpr, ParseError> {
+    finish(input, terminated(type_at(0), context("end of input", ws(eof)))(input))
+}
@@ -0,0 +1,162 @@
+use std::collections::{BTreeMap, BTreeSet};
+use bcbc::{CVariant, Def, EnumVariantId, Field, HashId, Registry, StdId, Tag, Type, TypeId, Variant, SCHEMA_ANONYMOUS, SCHEMA_HASH};
+use crate::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveError {
+    // schema 0x00 and 0xff
+    ReservedSchema { name: String, schema: u8 },
+    // named after a type keyword, e.g. `struct u8` or `struct list`, which could never be referred to
+    ReservedName(String),
+    DuplicateName(String),
+    DuplicateId { name: String, id: TypeId },
+    Undefined { name: String, decl: String },
+    DuplicateField { name: String, field: String },
+    DuplicateVariant { name: String, variant: String },
+    DuplicateVariantId { name: String, ev: EnumVariantId },
+    // not a type id for another reason, e.g. in a hand-built `Schema`
+    InvalidId { name: String, id: IdExpr },
+}
+
+impl std::fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolveError::ReservedSchema { name, schema } => write!(f, "reserved schema 0x{:02x} in the id of {}", schema, name),
+            ResolveError::ReservedName(name) => write!(f, "{} is a type keyword", name),
+            ResolveError::DuplicateName(name) => write!(f, "{} declared twice", name),
+            ResolveError::DuplicateId { name, id } => write!(f, "id {} of {} already declared", id, name),
+            ResolveError::Undefined { name, decl } => write!(f, "undefined type {} in {}", name, decl),
+            ResolveError::DuplicateField { name, field } => write!(f, "field {} of {} declared twice", field, name),
+            ResolveError::DuplicateVariant { name, variant } => write!(f, "variant {} of {} declared twice", variant, name),
+            ResolveError::DuplicateVariantId { name, ev } => write!(f, "variant id {} of {} declared twice", ev, name),
+            ResolveError::InvalidId { name, id } => write!(f, "invalid id {:?} of {}", id, name),
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolved {
+    pub registry: Registry,
+    // declared names to `Type::Alias(id)`, `Type::Struct(id)` and so on
+    pub types: BTreeMap<String, Type>,
+}
+
+impl IdExpr {
+    pub fn to_type_id(&self) -> Option<TypeId> {
+        match self {
+            IdExpr::Std { schema, id } => StdId::from_inner(*schema, *id).map(TypeId::Std),
+            IdExpr::Hash(path) => Some(TypeId::Hash(HashId::from_path(path))),
+        }
+    }
+}
+
+impl TypeExpr {
+    // `decl` is the declaration it appears in, for errors
+    pub fn resolve(&self, types: &BTreeMap<String, Type>, decl: &str) -> Result<Type, ResolveError> {
+        let resolve = |t: &TypeExpr| t.resolve(types, decl);
+        Ok(match self {
+            TypeExpr::Scalar(t) => t.clone(),
+            TypeExpr::Option(t) => Type::Option(Box::new(resolve(t)?)),
+            TypeExpr::List(t) => Type::List(Box::new(resolve(t)?)),
+            TypeExpr::Map(k, v) => Type::Map(Box::new(resolve(k)?), Box::new(resolve(v)?)),
+            TypeExpr::Tuple(ts) => Type::Tuple(ts.iter().map(resolve).collect::<Result<_, _>>()?),
+            TypeExpr::Named(name) => types.get(name).cloned().ok_or_else(|| ResolveError::Undefined {
+                name: name.clone(),
+                decl: decl.to_owned(),
+            })?,
+        })
+    }
+}
+
+// same as `#[derive(Schema)]`: counting up from the previous id, starting at 0
+fn variant_ids<'a>(name: &str, ids: impl Iterator<Item = (&'a str, Option<EnumVariantId>)>) -> Result<Vec<EnumVariantId>, ResolveError> {
+    let mut names = BTreeSet::new();
+    let mut seen = BTreeSet::new();
+    let mut next = 0u64;
+    let mut evs = Vec::new();
+    for (variant, id) in ids {
+        if !names.insert(variant) {
+            return Err(ResolveError::DuplicateVariant { name: name.to_owned(), variant: variant.to_owned() });
+        }
+        let ev = id.unwrap_or(next);
+        if !seen.insert(ev) {
+            return Err(ResolveError::DuplicateVariantId { name: name.to_owned(), ev });
+        }
+        evs.push(ev);
+        next = ev.wrapping_add(1);
+    }
+    Ok(evs)
+}
+
+impl Schema {
+    // declarations may refer to each other in any order
+    pub fn resolve(&self) -> Result<Resolved, ResolveError> {
+        let mut types = BTreeMap::new();
+        let mut ids = BTreeSet::new();
+        // in the order of the declarations
+        let mut decl_ids = Vec::new();
+        for Decl { name, id, kind } in &self.decls {
+            if Tag::from_name(name).is_some() {
+                return Err(ResolveError::ReservedName(name.clone()));
+            }
+            let id = id.to_type_id().ok_or_else(|| match id {
+                IdExpr::Std { schema: schema @ (SCHEMA_ANONYMOUS | SCHEMA_HASH), .. } => {
+                    ResolveError::ReservedSchema { name: name.clone(), schema: *schema }
+                }
+                id => ResolveError::InvalidId { name: name.clone(), id: id.clone() },
+            })?;
+            decl_ids.push(id);
+            if !ids.insert(id) {
+                return Err(ResolveError::DuplicateId { name: name.clone(), id });
+            }
+            let ty = match kind {
+                DeclKind::Alias(_) => Type::Alias(id),
+                DeclKind::Struct(_) => Type::Struct(id),
+                DeclKind::Enum(_) => Type::Enum(id),
+                DeclKind::CEnum(_) => Type::CEnum(id),
+            };
+            if types.insert(name.clone(), ty).is_some() {
+                return Err(ResolveError::DuplicateName(name.clone()));
+            }
+        }
+
+        let mut registry = Registry::new();
+        for (Decl { name, kind, .. }, id) in self.decls.iter().zip(decl_ids) {
+            let def = match kind {
+                DeclKind::Alias(ty) => Def::Alias { name: name.clone(), ty: ty.resolve(&types, name)? },
+                DeclKind::Struct(fields) => {
+                    let mut names = BTreeSet::new();
+                    let fields = fields.iter().map(|FieldDecl { name: field, ty }| {
+                        if !names.insert(field) {
+                            return Err(ResolveError::DuplicateField { name: name.clone(), field: field.clone() });
+                        }
+                        Ok(Field { name: field.clone(), ty: ty.resolve(&types, name)? })
+                    }).collect::<Result<_, _>>()?;
+                    Def::Struct { name: name.clone(), fields }
+                }
+                DeclKind::Enum(variants) => {
+                    let evs = variant_ids(name, variants.iter().map(|v| (v.name.as_str(), v.id)))?;
+                    let variants = variants.iter().zip(evs).map(|(VariantDecl { name: variant, ty, .. }, id)| Ok(Variant {
+                        id,
+                        name: variant.clone(),
+                        ty: match ty {
+                            Some(ty) => ty.resolve(&types, name)?,
+                            None => Type::Unit,
+                        },
+                    })).collect::<Result<_, _>>()?;
+                    Def::Enum { name: name.clone(), variants }
+                }
+                DeclKind::CEnum(variants) => {
+                    let evs = variant_ids(name, variants.iter().map(|v| (v.name.as_str(), v.id)))?;
+                    let variants = variants.iter().zip(evs).map(|(v, id)| CVariant { id, name: v.name.clone() }).collect();
+                    Def::CEnum { name: name.clone(), variants }
+                }
+            };
+            let _ = registry.insert(id, def);
+        }
+
+        Ok(Resolved { registry, types })
+    }
+}
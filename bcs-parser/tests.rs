@@ -0,0 +1,192 @@
+use bcbc::{CVariant, Def, Field, HashId, StdId, Type, TypeId, Variant};
+use crate::*;
+
+const SCHEMA: &str = r#"
+// a comment
+alias Names std(0x01, 0x5f50) = list<map<u64, string>>;
+
+struct Point hash("example::Point") {
+    x: i64,
+    y: i64,
+    tag: option<tuple<Kind, unit, unknown>>,
+}
+
+enum Shape std(1, 2) {
+    Empty,
+    Dot(Point) = 5,
+    Line(tuple<Point, Point>),
+}
+
+cenum Kind std(0x01, 0x03) { A, B = 10, C, }
+"#;
+
+#[test]
+fn parse_ast() {
+    let schema = parse(SCHEMA).unwrap();
+    assert_eq!(schema.decls.len(), 4);
+    assert_eq!(schema.decls[0], Decl {
+        name: "Names".to_owned(),
+        id: IdExpr::Std { schema: 0x01, id: 0x5f50 },
+        kind: DeclKind::Alias(TypeExpr::List(Box::new(TypeExpr::Map(
+            Box::new(TypeExpr::Scalar(Type::U64)),
+            Box::new(TypeExpr::Scalar(Type::String)),
+        )))),
+    });
+    assert_eq!(schema.decls[1].id, IdExpr::Hash("example::Point".to_owned()));
+    assert_eq!(schema.decls[3].kind, DeclKind::CEnum(vec![
+        CVariantDecl { name: "A".to_owned(), id: None },
+        CVariantDecl { name: "B".to_owned(), id: Some(10) },
+        CVariantDecl { name: "C".to_owned(), id: None },
+    ]));
+    assert_eq!(parse("").unwrap().decls, vec![]);
+    assert_eq!(parse_type("tuple<>").unwrap(), TypeExpr::Tuple(vec![]));
+    assert_eq!(parse_type(" typeid ").unwrap(), TypeExpr::Scalar(Type::TypeId));
+}
+
+#[test]
+fn resolve() {
+    let Resolved { registry, types } = parse(SCHEMA).unwrap().resolve().unwrap();
+    let names = TypeId::Std(StdId::from_inner(0x01, 0x5f50).unwrap());
+    let point = TypeId::Hash(HashId::from_path("example::Point"));
+    let shape = TypeId::Std(StdId::from_inner(0x01, 0x02).unwrap());
+    let kind = TypeId::Std(StdId::from_inner(0x01, 0x03).unwrap());
+    assert_eq!(types["Names"], Type::Alias(names));
+    assert_eq!(types["Kind"], Type::CEnum(kind));
+    assert_eq!(registry.len(), 4);
+    assert_eq!(registry.alias(&names), Some(&Type::List(Box::new(Type::Map(Box::new(Type::U64), Box::new(Type::String))))));
+    assert_eq!(registry.field(&point, 2), Some(&Field {
+        name: "tag".to_owned(),
+        ty: Type::Option(Box::new(Type::Tuple(Box::new([Type::CEnum(kind), Type::Unit, Type::Unknown])))),
+    }));
+    assert_eq!(registry.get(&shape), Some(&Def::Enum { name: "Shape".to_owned(), variants: vec![
+        Variant { id: 0, name: "Empty".to_owned(), ty: Type::Unit },
+        Variant { id: 5, name: "Dot".to_owned(), ty: Type::Struct(point) },
+        Variant { id: 6, name: "Line".to_owned(), ty: Type::Tuple(Box::new([Type::Struct(point), Type::Struct(point)])) },
+    ] }));
+    assert_eq!(registry.c_variant(&kind, 11), Some(&CVariant { id: 11, name: "C".to_owned() }));
+}
+
+#[test]
+fn parse_errors() {
+    let err = |s| parse(s).unwrap_err();
+    assert_eq!(err("structs A std(1, 1) {}"), ParseError { pos: 0, expected: "declaration" });
+    assert_eq!(err("structs A std(1, 1) {}").to_string(), "expected declaration at 0");
+    assert_eq!(err("struct A std(1, 1) { x: }"), ParseError { pos: 24, expected: "type" });
+    assert_eq!(err("struct A std(1, 1) { x u8 }"), ParseError { pos: 23, expected: "`:`" });
+    assert_eq!(err("alias A std(1, 0x10000) = u8;"), ParseError { pos: 15, expected: "number" });
+    assert_eq!(err("alias A hush(\"a\") = u8;"), ParseError { pos: 8, expected: "type id" });
+    assert_eq!(err("alias A std(1, 1) = list<u8;"), ParseError { pos: 27, expected: "`>`" });
+    assert_eq!(parse_type("u8 u8").unwrap_err(), ParseError { pos: 3, expected: "end of input" });
+    assert_eq!(parse_type("map<u8>").unwrap_err(), ParseError { pos: 6, expected: "`,`" });
+
+    let deep = |n| format!("{}u8{}", "list<".repeat(n), ">".repeat(n));
+    assert!(parse_type(&deep(MAX_DEPTH)).is_ok());
+    assert_eq!(parse_type(&deep(MAX_DEPTH + 1)).unwrap_err(), ParseError { pos: 5 * MAX_DEPTH, expected: "type within the depth limit" });
+    assert_eq!(parse_type(&deep(1_000_000)).unwrap_err(), ParseError { pos: 5 * MAX_DEPTH, expected: "type within the depth limit" });
+    let decl = format!("struct A std(1, 1) {{ x: {} }}", deep(1_000_000));
+    assert_eq!(parse(&decl).unwrap_err(), ParseError { pos: 24 + 5 * MAX_DEPTH, expected: "type within the depth limit" });
+}
+
+#[test]
+fn resolve_errors() {
+    let err = |s| parse(s).unwrap().resolve().unwrap_err();
+    assert_eq!(err("alias A std(0, 1) = u8;"), ResolveError::ReservedSchema { name: "A".to_owned(), schema: 0 });
+    assert_eq!(err("alias A std(1, 1) = u8; alias A std(1, 2) = u8;"), ResolveError::DuplicateName("A".to_owned()));
+    assert_eq!(err("struct u8 std(1, 1) { x: u8 }"), ResolveError::ReservedName("u8".to_owned()));
+    assert_eq!(err("alias list std(1, 1) = u8;").to_string(), "list is a type keyword");
+    assert_eq!(
+        err("alias A hash(\"x\") = u8; alias B hash(\"x\") = u8;"),
+        ResolveError::DuplicateId { name: "B".to_owned(), id: TypeId::Hash(HashId::from_path("x")) },
+    );
+    assert_eq!(err("struct A std(1, 1) { x: B }"), ResolveError::Undefined { name: "B".to_owned(), decl: "A".to_owned() });
+    assert_eq!(err("struct A std(1, 1) { x: u8, x: u8 }"), ResolveError::DuplicateField { name: "A".to_owned(), field: "x".to_owned() });
+    assert_eq!(err("cenum A std(1, 1) { X, X }"), ResolveError::DuplicateVariant { name: "A".to_owned(), variant: "X".to_owned() });
+    assert_eq!(err("enum A std(1, 1) { X = 1, Y = 0, Z }"), ResolveError::DuplicateVariantId { name: "A".to_owned(), ev: 1 });
+    assert_eq!(err("alias A std(0xff, 1) = u8;"), ResolveError::ReservedSchema { name: "A".to_owned(), schema: 0xff });
+    assert_eq!(
+        err("alias A std(0xff, 1) = u8;").to_string(),
+        "reserved schema 0xff in the id of A",
+    );
+    // out of range ids do not reach resolution
+    assert_eq!(parse("alias A std(0x100, 1) = u8;").unwrap_err(), ParseError { pos: 12, expected: "number" });
+    assert_eq!(parse("alias A std(1, 65536) = u8;").unwrap_err(), ParseError { pos: 15, expected: "number" });
+
+    // nor panic in hand-built schemas
+    let decl = |id| Decl { name: "A".to_owned(), id, kind: DeclKind::Alias(TypeExpr::Scalar(Type::U8)) };
+    let schema = Schema { decls: vec![decl(IdExpr::Std { schema: 0, id: 0xffff })] };
+    assert_eq!(schema.resolve().unwrap_err(), ResolveError::ReservedSchema { name: "A".to_owned(), schema: 0 });
+    let schema = Schema { decls: vec![decl(IdExpr::Hash(String::new()))] };
+    assert_eq!(schema.resolve().unwrap().registry.len(), 1);
+}
+
+const VALUE: &str = r#"tuple(unit, true, false, 255u8, 65535u16, 1u32, 18446744073709551615u64, -128i8, -7i16, 0i32, -7i64, 1.0f16, 65504.0f16, -0.0f32, 1.5f32, 1e-7f32, 0.1f64, 1e300f64, f16(0x7c00), f32(0x7fc00001), f64(0xfff0000000000000), 'a', '\'', '🦀', 340282366920938463463374607431768211455u128, -170141183460469231731687303715884105728i128, "", "a\"b\\c\n\u{7f}🦀", b"", b"a\"\\\x00\xff", some<bool>(true), none<string>, some<option<u8>>(none<u8>), list<string>[], list<string>["a", "b"], map<u64, string>{}, map<u64, string>{1u64: "a", 2u64: "b"}, tuple(), alias#std:01:5f50(list<u8>[1u8]), cenum#std:01:5f49(7), enum#hash:fedcba98765432(5, tuple(1u8, unit)), struct#anon(), struct#std:01:0002(0u8, "x"), type(map<char, tuple<u128, i128>>), typeid(anon), typeid(std:01:5f50), typeid(hash:fedcba98765432))"#;
+
+#[test]
+fn value_text() {
+    use bcbc::{byte_storage::{SliceInput, VecOutput}, Value};
+
+    let val = parse_value(VALUE).unwrap();
+    assert_eq!(val.to_string(), VALUE);
+    let buf = val.encode::<VecOutput>().unwrap();
+    let decoded = Value::decode::<SliceInput>(buf.as_slice()).unwrap();
+    assert_eq!(decoded.to_string(), VALUE);
+    assert_eq!(parse_value(&format!("{:#}", val)).unwrap(), val);
+
+    let Value::Tuple(s) = &val else { unreachable!() };
+    assert_eq!(s[11], Value::F16(0x3c00));
+    assert_eq!(s[14], Value::F32(1.5f32.to_bits()));
+    assert_eq!(s[19], Value::F32(0x7fc00001));
+    assert_eq!(s[29], Value::Bytes(vec![b'a', b'"', b'\\', 0x00, 0xff]));
+    assert_eq!(s[30], Value::Option(Type::Bool, Box::new(Some(Value::Bool(true)))));
+    assert_eq!(s[40], Value::Enum(
+        TypeId::Hash(HashId::from_hash([0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54, 0x32])),
+        5,
+        Box::new(Value::Tuple(Box::new([Value::U8(1), Value::Unit]))),
+    ));
+
+    assert_eq!(format!("{:#}", parse_value("list<tuple<>>[tuple(), tuple()]").unwrap()), "list<tuple<>>[\n    tuple(),\n    tuple(),\n]");
+    assert_eq!(
+        format!("{:#}", parse_value("struct#anon(map<u8, u8>{1u8: 2u8}, list<u8>[])").unwrap()),
+        "struct#anon(\n    map<u8, u8>{\n        1u8: 2u8,\n    },\n    list<u8>[],\n)",
+    );
+    // not canonical, but accepted
+    assert_eq!(parse_value("'\\u{1f980}'").unwrap(), Value::Char('🦀'));
+    assert_eq!(parse_value(" tuple ( 1u8 , 2u8 , ) ").unwrap().to_string(), "tuple(1u8, 2u8)");
+}
+
+#[test]
+fn value_errors() {
+    let err = |s| parse_value(s).unwrap_err();
+    assert_eq!(err(""), ParseError { pos: 0, expected: "value" });
+    assert_eq!(err("1"), ParseError { pos: 1, expected: "number suffix" });
+    assert_eq!(err("256u8"), ParseError { pos: 0, expected: "number in range of u8" });
+    assert_eq!(err("1.5u8"), ParseError { pos: 0, expected: "number in range of u8" });
+    assert_eq!(err("\"abc"), ParseError { pos: 4, expected: "closing quote" });
+    assert_eq!(err("b\"\\u{1}\""), ParseError { pos: 3, expected: "escape" });
+    assert_eq!(err("'ab'"), ParseError { pos: 0, expected: "single char" });
+    assert_eq!(err("list<u8>[1u8 2u8]"), ParseError { pos: 13, expected: "`]`" });
+    assert_eq!(err("list<u9>[]"), ParseError { pos: 5, expected: "type" });
+    assert_eq!(err("some<u8>"), ParseError { pos: 8, expected: "`(`" });
+    assert_eq!(err("typeid(std:00:0001)"), ParseError { pos: 7, expected: "non-reserved schema" });
+    assert_eq!(err("f32(0x7fc0)"), ParseError { pos: 6, expected: "hex digits" });
+    assert_eq!(err("unit unit"), ParseError { pos: 5, expected: "end of input" });
+
+    let deep = |n| format!("{}unit{}", "tuple(".repeat(n), ")".repeat(n));
+    assert!(parse_value(&deep(MAX_DEPTH)).is_ok());
+    let limit = ParseError { pos: 6 * MAX_DEPTH, expected: "value within the depth limit" };
+    assert_eq!(parse_value(&deep(MAX_DEPTH + 1)).unwrap_err(), limit);
+    assert_eq!(parse_value(&deep(1_000_000)).unwrap_err(), limit);
+    let deep = "some<unit>(".repeat(1_000_000);
+    assert_eq!(parse_value(&deep).unwrap_err(), ParseError { pos: 11 * MAX_DEPTH, expected: "value within the depth limit" });
+}
+
+#[test]
+fn f16_literal() {
+    use bcbc::Value;
+
+    // 1 + 2^-11 + 2^-30 is above the halfway point between 1 and the next f16, but rounds to exactly halfway as f32
+    assert_eq!(parse_value("1.000488282181322574615478515625f16").unwrap(), Value::F16(0x3c01));
+    assert_eq!(parse_value("1.00048828125f16").unwrap(), Value::F16(0x3c00));
+    assert_eq!(parse_value("65520.0f16").unwrap(), Value::F16(0x7c00));
+    assert_eq!(parse_value("-1e300f16").unwrap(), Value::F16(0xfc00));
+}
@@ -0,0 +1,215 @@
+// parser of the value literal notation printed by `Value`'s `Display`, see `bcbc/text.rs`
+
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_while, take_while_m_n},
+    character::complete::{char, digit1, one_of},
+    combinator::{cut, eof, map, opt, recognize},
+    sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
+};
+use bcbc::{casting, Type, TypeId, Value};
+use crate::*;
+
+type V = Value<Vec<u8>>;
+
+fn fail<'a, T>(rest: &'a str, expected: &'static str) -> PResult<'a, T> {
+    Err(nom::Err::Failure(Error { rest, expected }))
+}
+
+// the text notation of `Type`, parsed by bcbc
+fn bcbc_type(input: &str) -> PResult<'_, Type> {
+    let (input, ()) = skip(input)?;
+    match Type::parse_prefix(input) {
+        Ok((t, rest)) => Ok((rest, t)),
+        Err(e) => fail(&input[e.pos..], e.expected),
+    }
+}
+
+fn hex_digits<'a>(n: usize) -> impl FnMut(&'a str) -> PResult<'a, &'a str> {
+    context("hex digits", take_while_m_n(n, n, |c: char| c.is_ascii_hexdigit()))
+}
+
+// `anon`, `std:01:5f50` or `hash:fedcba98765432`, parsed by bcbc
+fn type_id(input: &str) -> PResult<'_, TypeId> {
+    let (input, ()) = skip(input)?;
+    let (rest, s) = take_while(|c: char| c.is_ascii_alphanumeric() || c == ':')(input)?;
+    match s.parse() {
+        Ok(id) => Ok((rest, id)),
+        Err(e) => fail(&input[e.pos..], e.expected),
+    }
+}
+
+// `\n`, `\r`, `\t`, `\\`, `\0`, `\'`, `\"` and `\u{..}` as printed by `char::escape_debug`, `bytes` allows `\xNN`
+// instead of `\u{..}`
+fn escape(input: &str, bytes: bool) -> PResult<'_, u32> {
+    let (rest, c) = context("escape", one_of("nrt\\0'\"ux"))(input)?;
+    match c {
+        'n' => Ok((rest, '\n' as u32)),
+        'r' => Ok((rest, '\r' as u32)),
+        't' => Ok((rest, '\t' as u32)),
+        '0' => Ok((rest, 0)),
+        'u' if !bytes => {
+            let (rest, digits) = delimited(
+                char('{'),
+                context("hex digits", take_while_m_n(1, 6, |c: char| c.is_ascii_hexdigit())),
+                char('}'),
+            )(rest)?;
+            Ok((rest, u32::from_str_radix(digits, 16).unwrap()))
+        }
+        'x' if bytes => map(hex_digits(2), |s| u32::from_str_radix(s, 16).unwrap())(rest),
+        'u' | 'x' => fail(input, "escape"),
+        c => Ok((rest, c as u32)),
+    }
+}
+
+// the content up to the closing `quote`
+fn quoted(input: &str, quote: char, bytes: bool) -> PResult<'_, Vec<u32>> {
+    let mut out = Vec::new();
+    let mut rest = input;
+    loop {
+        let mut chars = rest.chars();
+        match chars.next() {
+            None => return fail(rest, "closing quote"),
+            Some(c) if c == quote => return Ok((chars.as_str(), out)),
+            Some('\\') => {
+                let (r, c) = escape(chars.as_str(), bytes)?;
+                out.push(c);
+                rest = r;
+            }
+            Some(c) if bytes && !c.is_ascii() => return fail(rest, "ASCII or escape"),
+            Some(c) => {
+                out.push(c as u32);
+                rest = chars.as_str();
+            }
+        }
+    }
+}
+
+fn string(input: &str) -> PResult<'_, V> {
+    let (rest, s) = preceded(char('"'), cut(|i| quoted(i, '"', false)))(input)?;
+    match s.into_iter().map(char::from_u32).collect::<Option<String>>() {
+        Some(s) => Ok((rest, Value::String(s.into()))),
+        None => fail(input, "valid escape"),
+    }
+}
+
+fn character(input: &str) -> PResult<'_, V> {
+    let (rest, s) = preceded(char('\''), cut(|i| quoted(i, '\'', false)))(input)?;
+    match s[..] {
+        [c] => match char::from_u32(c) {
+            Some(c) => Ok((rest, Value::Char(c))),
+            None => fail(input, "valid escape"),
+        },
+        _ => fail(input, "single char"),
+    }
+}
+
+fn bytes(input: &str) -> PResult<'_, V> {
+    let (rest, b) = preceded(tag("b\""), cut(|i| quoted(i, '"', true)))(input)?;
+    Ok((rest, Value::Bytes(b.into_iter().map(|b| b as u8).collect())))
+}
+
+// decimal, with an optional fraction and exponent for floats, and the type as suffix
+fn number_literal(input: &str) -> PResult<'_, V> {
+    let (rest, digits) = recognize(tuple((
+        opt(char('-')),
+        digit1,
+        opt(pair(char('.'), digit1)),
+        opt(tuple((one_of("eE"), opt(one_of("+-")), digit1))),
+    )))(input)?;
+    let (rest, suffix) = take_while(|c: char| c.is_ascii_alphanumeric())(rest)?;
+    macro_rules! parse {
+        ($($suffix:literal => $t:ty, $f:expr;)*) => {
+            match suffix {
+                $($suffix => match digits.parse::<$t>() {
+                    Ok(n) => Ok((rest, $f(n))),
+                    Err(_) => fail(input, concat!("number in range of ", $suffix)),
+                },)*
+                _ => fail(&input[digits.len()..], "number suffix"),
+            }
+        };
+    }
+    parse! {
+        "u8" => u8, Value::U8;
+        "u16" => u16, Value::U16;
+        "u32" => u32, Value::U32;
+        "u64" => u64, Value::U64;
+        "u128" => u128, Value::U128;
+        "i8" => i8, Value::I8;
+        "i16" => i16, Value::I16;
+        "i32" => i32, Value::I32;
+        "i64" => i64, Value::I64;
+        "i128" => i128, Value::I128;
+        "f16" => f64, |n| Value::F16(casting::f64_to_f16(n));
+        "f32" => f32, |n: f32| Value::F32(n.to_bits());
+        "f64" => f64, |n: f64| Value::F64(n.to_bits());
+    }
+}
+
+fn args<'a, T>(f: impl FnMut(&'a str) -> PResult<'a, T>) -> impl FnMut(&'a str) -> PResult<'a, T> {
+    cut(delimited(sym('('), f, sym(')')))
+}
+
+fn seq<'a>(open: char, close: char, depth: usize) -> impl FnMut(&'a str) -> PResult<'a, Box<[V]>> {
+    map(cut(delimited(sym(open), list_of(value_at(depth)), sym(close))), Vec::into_boxed_slice)
+}
+
+// floats that have no decimal notation, i.e. NaNs and infinities
+fn bits<'a>(digits: usize) -> impl FnMut(&'a str) -> PResult<'a, u64> {
+    map(args(preceded(tag("0x"), hex_digits(digits))), |s| u64::from_str_radix(s, 16).unwrap())
+}
+
+// everything starting with a word, `depth` containers deep
+fn word(input: &str, depth: usize) -> PResult<'_, V> {
+    // last in `alt`, so this names the error if nothing matches
+    let (rest, w) = context("value", ident)(input)?;
+    let inner = depth + 1;
+    if let "some" | "tuple" | "list" | "map" | "alias" | "enum" | "struct" = w {
+        let _ = enter(input, depth, "value within the depth limit")?;
+    }
+    match w {
+        "unit" => Ok((rest, Value::Unit)),
+        "true" => Ok((rest, Value::Bool(true))),
+        "false" => Ok((rest, Value::Bool(false))),
+        "f16" => map(bits(4), |b| Value::F16(b as u16))(rest),
+        "f32" => map(bits(8), |b| Value::F32(b as u32))(rest),
+        "f64" => map(bits(16), Value::F64)(rest),
+        "some" => map(
+            pair(cut(delimited(sym('<'), bcbc_type, sym('>'))), args(value_at(inner))),
+            |(t, v)| Value::Option(t, Box::new(Some(v))),
+        )(rest),
+        "none" => map(cut(delimited(sym('<'), bcbc_type, sym('>'))), |t| Value::Option(t, Box::new(None)))(rest),
+        "tuple" => map(seq('(', ')', inner), Value::Tuple)(rest),
+        "type" => map(args(bcbc_type), Value::Type)(rest),
+        "typeid" => map(args(type_id), Value::TypeId)(rest),
+        "list" | "map" | "alias" | "cenum" | "enum" | "struct" => {
+            let (rest, t) = bcbc_type(input)?;
+            match t {
+                Type::List(t) => {
+                    let (rest, s) = seq('[', ']', inner)(rest)?;
+                    Ok((rest, Value::List(*t, s)))
+                }
+                Type::Map(tk, tv) => {
+                    let entry = separated_pair(value_at(inner), cut(sym(':')), cut(value_at(inner)));
+                    let (rest, s) = cut(delimited(sym('{'), list_of(entry), sym('}')))(rest)?;
+                    Ok((rest, Value::Map((*tk, *tv), s.into_boxed_slice())))
+                }
+                Type::Alias(id) => map(args(value_at(inner)), |v| Value::Alias(id, Box::new(v)))(rest),
+                Type::CEnum(id) => map(args(number), |ev| Value::CEnum(id, ev))(rest),
+                Type::Enum(id) => map(args(separated_pair(number, sym(','), value_at(inner))), |(ev, v)| Value::Enum(id, ev, Box::new(v)))(rest),
+                Type::Struct(id) => map(seq('(', ')', inner), |s| Value::Struct(id, s))(rest),
+                _ => unreachable!(),
+            }
+        }
+        _ => Err(nom::Err::Error(Error { rest: input, expected: "value" })),
+    }
+}
+
+// a value inside `depth` containers
+fn value_at<'a>(depth: usize) -> impl FnMut(&'a str) -> PResult<'a, V> {
+    move |input| ws(alt((number_literal, string, character, bytes, |i| word(i, depth))))(input)
+}
+
+pub fn parse_value(input: &str) -> Result<Value<Vec<u8>>, ParseError> {
+    finish(input, terminated(value_at(0), context("end of input", ws(eof)))(input))
+}
@@ -105,6 +105,35 @@ impl<B> ByteStr<B> {
     }
 }
 
+// as strings, borrowing from the input if possible
+#[cfg(feature = "serde")]
+impl<B: AsRef<[u8]> + ByteStorage> serde::Serialize for ByteStr<B> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(AsRef::<str>::as_ref(self))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de: 'a, 'a> serde::Deserialize<'de> for ByteStr<&'a [u8]> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <&'a str as serde::Deserialize>::deserialize(deserializer).map(Into::into)
+    }
+}
+
+#[cfg(all(feature = "serde", feature = "alloc"))]
+impl<'de> serde::Deserialize<'de> for ByteStr<Vec<u8>> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <String as serde::Deserialize>::deserialize(deserializer).map(Into::into)
+    }
+}
+
+#[cfg(all(feature = "serde", feature = "alloc", feature = "bytes"))]
+impl<'de> serde::Deserialize<'de> for ByteStr<Bytes> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <String as serde::Deserialize>::deserialize(deserializer).map(Into::into)
+    }
+}
+
 #[test]
 const fn test() {
     trait AssertImpl { const ASSERT: () = (); }
@@ -113,6 +113,19 @@ pub enum ReadError {
     TooLongReadLen(usize),
 }
 
+impl core::fmt::Display for ReadError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ReadError::TooShort { rest, expected } => write!(f, "unexpected end of input: {} bytes needed, {} left", expected, rest),
+            ReadError::TooLong { rest } => write!(f, "{} trailing bytes", rest),
+            ReadError::TooLongReadLen(len) => write!(f, "read length {} overflows", len),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ReadError {}
+
 type Result<T> = core::result::Result<T, ReadError>;
 
 pub struct Reader<I> {
@@ -128,6 +141,11 @@ impl<B: AsRef<[u8]>, I: Input<Storage = B>> Reader<I> {
         }
     }
 
+    #[inline(always)]
+    pub const fn pos(&self) -> usize {
+        self.pos
+    }
+
     #[inline(always)]
     pub /* const */ fn rest_len(&self) -> usize {
         self.input.len() - self.pos
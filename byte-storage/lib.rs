@@ -2,6 +2,8 @@
 
 #![no_std]
 
+#[cfg(feature = "std")]
+extern crate std;
 #[cfg(feature = "alloc")]
 extern crate alloc;
 #[cfg(feature = "alloc")]
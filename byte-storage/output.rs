@@ -3,10 +3,28 @@ use crate::*;
 // TODO should we make no-panic guarantees like reader::Input ?
 // before this, Output does not need to be unsafe
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WriteError {
+    Full { rest: usize, expected: usize },
+}
+
+impl core::fmt::Display for WriteError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            WriteError::Full { rest, expected } => write!(f, "output full: {} bytes to write, {} left", expected, rest),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for WriteError {}
+
+type Result<T> = core::result::Result<T, WriteError>;
+
 pub trait Output: Default {
     type Storage: ByteStorage; // ?
-    fn byte(&mut self, n: u8);
-    fn bytes<B: AsRef<[u8]>>(&mut self, bytes: B);
+    fn byte(&mut self, n: u8) -> Result<()>;
+    fn bytes<B: AsRef<[u8]>>(&mut self, bytes: B) -> Result<()>;
     fn leak(self) -> Self::Storage;
 }
 
@@ -21,13 +39,15 @@ impl Output for VecOutput {
     type Storage = Vec<u8>;
 
     #[inline]
-    fn byte(&mut self, n: u8) {
+    fn byte(&mut self, n: u8) -> Result<()> {
         self.bytes.push(n);
+        Ok(())
     }
 
     #[inline]
-    fn bytes<B: AsRef<[u8]>>(&mut self, bytes: B) {
+    fn bytes<B: AsRef<[u8]>>(&mut self, bytes: B) -> Result<()> {
         self.bytes.extend_from_slice(bytes.as_ref());
+        Ok(())
     }
 
     fn leak(self) -> Self::Storage {